@@ -0,0 +1,1313 @@
+// Non-interactive subcommands for scripting, e.g.
+//   yada log add --user atharv --date 2025-04-08 egg 2
+// They read and write the same data files as the interactive menu, and every command
+// can print JSON instead of text with --json.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+
+use chrono::{Duration, Local, NaiveDate, NaiveTime, Timelike};
+
+use crate::{
+    ActivityDetails, ActivityLevel, CalculatorRegistry, CommandType, DailyLog, Exercise, ExerciseDatabase,
+    ExerciseEntry, Food, FoodDatabase,
+    FoodDatabaseError, Gender, Journal, EnergyUnit, GoalProjection, Macros, Meal, ProfileHistory, Quantity, ServingSize,
+    UnitPreferences, UnitSystem, UserManager, UserProfile, Username, WeightGoal, WorkoutLog, WorkoutSet, calories_burned,
+    format_amount, local_date_of, local_time_of, parse_duration,
+};
+use crate::clock::SystemClock;
+use crate::config::{Config, Locale};
+use crate::import;
+use crate::storage::{self, Storage, StorageKind};
+use crate::workout::{MuscleGroup, describe_load, parse_rpe};
+
+const EXIT_OK: i32 = 0;
+const EXIT_FAILURE: i32 = 1; // the command was understood but couldn't be carried out
+const EXIT_USAGE: i32 = 2; // bad or missing arguments
+
+const USAGE: &str = "Usage:
+  yada log add --user <name> [--date YYYY-MM-DD] [--meal <meal>] [--time HH:MM] <food-id> <amount>
+  yada log show --user <name> [--date YYYY-MM-DD]
+  yada food search [--all] <keyword>...
+  yada food add <id> <name> <calories> [--keywords a,b] [--macros p,c,f,fiber,sugar] [--serving \"100 g\"] [--user <name>]
+  yada exercise search [--all] [<keyword>...]
+  yada exercise add <id> <name> <met> [--keywords a,b]
+  yada exercise log --user <name> [--date YYYY-MM-DD] [--time HH:MM] <exercise-id> <duration>
+  yada exercise import --user <name> [--activity <exercise-id>] <file>...
+  yada workout log --user <name> [--date YYYY-MM-DD] [--time HH:MM] [--rpe <1-10>] [--muscles a,b] <lift> <reps> <weight>
+  yada workout show --user <name> [--date YYYY-MM-DD]
+  yada workout records --user <name>
+  yada profile show --user <name> [--date YYYY-MM-DD]
+  yada profile set --user <name> [--date YYYY-MM-DD] <field> <value>
+      fields: weight, height, age, gender, activity, method, body-fat, waist, hip, neck
+      (\"none\" clears body-fat, waist, hip and neck)
+  yada goal show --user <name> [--date YYYY-MM-DD]
+  yada goal set --user <name> [--date YYYY-MM-DD] <target-weight> <weekly-change> [--min-calories <n>]
+  yada goal clear --user <name> [--date YYYY-MM-DD]
+  yada units show --user <name>
+  yada units set --user <name> <metric|imperial|kcal|kj>...
+  yada report --user <name> [--from YYYY-MM-DD] [--to YYYY-MM-DD]
+  yada migrate --from <text|sqlite> --to <text|sqlite> [--force]
+
+Add --json to any command for machine-readable output. <amount> is a number of
+servings (e.g. 2 or 0.5) or a quantity such as \"150 g\". <duration> is in minutes
+unless it says otherwise, e.g. 45, \"1.5 h\" or 1:30. Activities in imported FIT,
+GPX and TCX files are logged on the dates they were recorded, as the --activity
+given or else the exercise matching their sport, and skipped if already imported.
+A workout <weight> of 0 is a bodyweight set, and a lift that hasn't been logged
+before needs --muscles, e.g. chest,triceps. Weights, heights and energy are read
+and shown in the user's units; a value may name its own unit, e.g. \"180 lb\",
+\"5 ft 10 in\" or \"8000 kJ\". JSON output is always in kg, cm and kcal.
+
+Settings such as --data-dir <dir>, --storage <text|sqlite>, --units <metric|imperial>,
+--energy <kcal|kj> and --method <method> may be given anywhere, or in the config file
+named by --config <file> (by default ~/.config/yada/yada.conf).";
+
+// Options that don't take a value
+const FLAGS: [&str; 3] = ["json", "all", "force"];
+
+enum CliError {
+    Usage(String),
+    Failure(String),
+}
+
+impl From<FoodDatabaseError> for CliError {
+    fn from(e: FoodDatabaseError) -> Self {
+        CliError::Failure(e.to_string())
+    }
+}
+
+// Minimal JSON value, enough for the command output
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    fn string(s: &str) -> Self {
+        Json::String(s.to_string())
+    }
+
+    fn macros(macros: &Macros) -> Self {
+        Json::Object(vec![
+            ("protein", Json::Number(macros.protein)),
+            ("carbs", Json::Number(macros.carbs)),
+            ("fat", Json::Number(macros.fat)),
+            ("fiber", Json::Number(macros.fiber)),
+            ("sugar", Json::Number(macros.sugar)),
+        ])
+    }
+
+    fn food(food: &Food) -> Self {
+        Json::Object(vec![
+            ("id", Json::string(&food.id)),
+            ("name", Json::string(&food.name)),
+            ("keywords", Json::Array(food.keywords.iter().map(|k| Json::string(k)).collect())),
+            ("calories_per_serving", Json::Number(food.calories_per_serving as f64)),
+            ("macros", Json::macros(&food.macros)),
+            ("serving", food.serving.map_or(Json::Null, |serving| Json::String(serving.to_string()))),
+            ("composite", Json::Bool(food.is_composite)),
+        ])
+    }
+
+    fn exercise(exercise: &Exercise) -> Self {
+        Json::Object(vec![
+            ("id", Json::string(&exercise.id)),
+            ("name", Json::string(&exercise.name)),
+            ("keywords", Json::Array(exercise.keywords.iter().map(|k| Json::string(k)).collect())),
+            ("met", Json::Number(exercise.met)),
+        ])
+    }
+
+    fn set(index: usize, set: &WorkoutSet) -> Self {
+        Json::Object(vec![
+            ("index", Json::Number(index as f64)),
+            ("lift", Json::string(&set.lift)),
+            ("muscles", Json::Array(set.muscles.iter().map(|muscle| Json::String(muscle.to_string())).collect())),
+            ("reps", Json::Number(set.reps as f64)),
+            ("weight", Json::Number(set.weight)),
+            ("rpe", set.rpe.map_or(Json::Null, Json::Number)),
+            ("time", Json::String(set.time.format("%H:%M").to_string())),
+            ("estimated_1rm", Json::Number(set.estimated_1rm())),
+        ])
+    }
+
+    // What a device recorded for an exercise entry, or null if it was logged by hand
+    fn activity(details: Option<&ActivityDetails>) -> Self {
+        let optional = |value: Option<f64>| value.map_or(Json::Null, Json::Number);
+        details.map_or(Json::Null, |details| Json::Object(vec![
+            ("start", Json::Number(details.start as f64)),
+            ("distance_m", optional(details.distance)),
+            ("ascent_m", optional(details.ascent)),
+            ("average_heart_rate", optional(details.heart_rate.map(f64::from))),
+            ("device_calories", optional(details.calories.map(f64::from))),
+        ]))
+    }
+
+    fn profile(profile: &UserProfile) -> Self {
+        Json::Object(vec![
+            ("username", Json::string(&profile.username)),
+            ("gender", Json::String(format!("{:?}", profile.gender))),
+            ("height_cm", Json::Number(profile.height)),
+            ("age", Json::Number(profile.age as f64)),
+            ("weight_kg", Json::Number(profile.weight)),
+            ("activity_level", Json::String(format!("{:?}", profile.activity_level))),
+            ("calculation_method", Json::string(&profile.calculation_method)),
+            ("maintenance_calories", Json::Number(profile.maintenance_calories() as f64)),
+            ("target_calories", Json::Number(profile.get_target_calories() as f64)),
+            ("goal", profile.goal.map_or(Json::Null, |goal| Json::goal(&goal))),
+            ("body_fat_percent", profile.body.body_fat.map_or(Json::Null, Json::Number)),
+            ("waist_cm", profile.body.waist.map_or(Json::Null, Json::Number)),
+            ("hip_cm", profile.body.hip.map_or(Json::Null, Json::Number)),
+            ("neck_cm", profile.body.neck.map_or(Json::Null, Json::Number)),
+            ("navy_body_fat_percent", profile.body.navy_body_fat(profile.gender, profile.height).map_or(Json::Null, Json::Number)),
+            ("lean_body_mass_kg", Json::Number(profile.lean_body_mass())),
+        ])
+    }
+
+    fn goal(goal: &WeightGoal) -> Self {
+        Json::Object(vec![
+            ("target_weight_kg", Json::Number(goal.target_weight)),
+            ("weekly_rate_kg", Json::Number(goal.weekly_rate)),
+            ("min_calories", goal.min_calories.map_or(Json::Null, |min| Json::Number(min as f64))),
+        ])
+    }
+
+    fn date(date: Option<NaiveDate>) -> Self {
+        date.map_or(Json::Null, |date| Json::String(date.format("%Y-%m-%d").to_string()))
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) if n.is_finite() => write!(f, "{}", n),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(s) => {
+                write!(f, "\"")?;
+                for c in s.chars() {
+                    match c {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        '\r' => write!(f, "\\r")?,
+                        '\t' => write!(f, "\\t")?,
+                        c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                write!(f, "\"")
+            },
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            },
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}:{}", Json::string(key), value)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+// What a command prints: plain text, or the JSON value with --json
+struct Output {
+    text: String,
+    json: Json,
+}
+
+// Command-line arguments split into positionals, `--key value` options and flags
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    // Every value ends up on one line of a data file, so none may contain a line break
+    fn parse(args: &[String]) -> Result<Self, CliError> {
+        if let Some(arg) = args.iter().find(|arg| arg.contains(['\n', '\r'])) {
+            return Err(CliError::Usage(format!("arguments must not contain line breaks: {:?}", arg)));
+        }
+
+        let mut parsed = Args {
+            positional: Vec::new(),
+            options: HashMap::new(),
+            flags: Vec::new(),
+        };
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.strip_prefix("--") {
+                Some(name) if FLAGS.contains(&name) => parsed.flags.push(name.to_string()),
+                Some(name) => {
+                    let value = iter.next()
+                        .ok_or_else(|| CliError::Usage(format!("--{} needs a value", name)))?;
+                    parsed.options.insert(name.to_string(), value.clone());
+                },
+                None => parsed.positional.push(arg.clone()),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f == name)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(|s| s.as_str())
+    }
+
+    fn required(&self, name: &str) -> Result<&str, CliError> {
+        self.option(name).ok_or_else(|| CliError::Usage(format!("--{} is required", name)))
+    }
+
+    fn date(&self, name: &str) -> Result<Option<NaiveDate>, CliError> {
+        match self.option(name) {
+            Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map(Some)
+                .map_err(|_| CliError::Usage(format!("--{} must be a date in YYYY-MM-DD format", name))),
+            None => Ok(None),
+        }
+    }
+}
+
+fn load_food_database(storage: &dyn Storage) -> Result<FoodDatabase, CliError> {
+    let mut database = FoodDatabase::new();
+    match storage.load_foods(&mut database) {
+        Ok(()) => {},
+        Err(FoodDatabaseError::Io(e)) => {
+            return Err(CliError::Failure(format!("could not load food database: {}", e)));
+        },
+        // The other foods are still usable
+        Err(e) => eprintln!("warning: {}", e),
+    }
+    Ok(database)
+}
+
+// The stored exercise database, or the common activities if none has been saved yet
+fn load_exercise_database(storage: &dyn Storage) -> Result<ExerciseDatabase, CliError> {
+    storage.load_exercises()
+        .map(|database| database.unwrap_or_else(ExerciseDatabase::with_common_activities))
+        .map_err(|e| CliError::Failure(format!("could not load exercise database: {}", e)))
+}
+
+// Users written by a newer version are refused like any other data, and never replaced
+fn load_users(storage: &Rc<dyn Storage>) -> Result<UserManager, CliError> {
+    UserManager::new(Rc::clone(storage)).map_err(|e| CliError::Failure(format!("could not load users: {}", e)))
+}
+
+// Everything stored for one user, loaded without any prompts
+struct UserSession {
+    storage: Rc<dyn Storage>,
+    username: Username,
+    food_database: FoodDatabase,
+    exercise_database: ExerciseDatabase,
+    daily_log: DailyLog,
+    workout_log: WorkoutLog,
+    profile_history: ProfileHistory,
+    journal: Journal,
+    units: UnitPreferences,
+}
+
+impl UserSession {
+    fn open(storage: &Rc<dyn Storage>, config: &Config, username: &str) -> Result<Self, CliError> {
+        let user_manager = load_users(storage)?;
+        let username = user_manager.find_user(username)
+            .ok_or_else(|| CliError::Failure(format!("no user named '{}'", username)))?;
+
+        let food_database = load_food_database(storage.as_ref())?;
+        let exercise_database = load_exercise_database(storage.as_ref())?;
+
+        let mut daily_log = storage.load_log(&username)
+            .map_err(|e| CliError::Failure(format!("could not load daily log: {}", e)))?;
+        daily_log.fill_missing_snapshots(&food_database);
+
+        let workout_log = storage.load_workouts(&username)
+            .map_err(|e| CliError::Failure(format!("could not load workout log: {}", e)))?;
+
+        let profile_history = storage.load_profile(&username)
+            .map_err(|e| CliError::Failure(format!("could not load profile: {}", e)))?;
+        for warning in profile_history.unknown_method_warnings() {
+            eprintln!("warning: {}", warning);
+        }
+
+        let journal = storage.load_journal(&username)
+            .map_err(|e| CliError::Failure(format!("could not load undo history: {}", e)))?;
+
+        let units = storage.load_preferences(&username)
+            .map_err(|e| CliError::Failure(format!("could not load unit preferences: {}", e)))?
+            .unwrap_or(config.units);
+
+        Ok(UserSession {
+            storage: Rc::clone(storage), username, food_database, exercise_database, daily_log, workout_log, profile_history,
+            journal, units,
+        })
+    }
+
+    // The profile in effect on `date`
+    fn profile(&self, date: &str) -> Result<&UserProfile, CliError> {
+        self.profile_history.on(date)
+            .ok_or_else(|| CliError::Failure("user has no profile yet; log in interactively to create one".to_string()))
+    }
+
+    // Records `profile` as the profile from `date` on, until the next change
+    fn record_profile_change(&mut self, date: &str, profile: UserProfile) {
+        let before = self.profile_history.record_at(date).cloned();
+        self.profile_history.set(date, profile.clone());
+        self.journal.record(CommandType::UpdateProfile(date.to_string(), before, profile));
+    }
+
+    fn save(&self) -> Result<(), CliError> {
+        let failure = |what: &str, e: std::io::Error| CliError::Failure(format!("could not save {}: {}", what, e));
+
+        self.storage.save_log(&self.username, &self.daily_log).map_err(|e| failure("daily log", e))?;
+        self.storage.save_workouts(&self.username, &self.workout_log).map_err(|e| failure("workout log", e))?;
+        self.storage.save_journal(&self.username, &self.journal).map_err(|e| failure("undo history", e))?;
+        if !self.profile_history.is_empty() {
+            self.storage.save_profile(&self.username, &self.profile_history).map_err(|e| failure("profile", e))?;
+        }
+        Ok(())
+    }
+}
+
+// Runs the subcommand in `args` (without the program name) and returns the exit code
+pub(crate) fn run(args: &[String], storage: Rc<dyn Storage>, config: &Config) -> i32 {
+    let result = Args::parse(args).and_then(|args| {
+        let command: Vec<&str> = args.positional.iter().map(|s| s.as_str()).collect();
+        let output = match command.as_slice() {
+            ["log", "add", rest @ ..] => log_add(&storage, config, &args, rest),
+            ["log", "show"] => log_show(&storage, config, &args),
+            ["food", "search", keywords @ ..] => food_search(&storage, config, &args, keywords),
+            ["food", "add", rest @ ..] => food_add(&storage, config, &args, rest),
+            ["exercise", "search", keywords @ ..] => exercise_search(&storage, &args, keywords),
+            ["exercise", "add", rest @ ..] => exercise_add(&storage, &args, rest),
+            ["exercise", "log", rest @ ..] => exercise_log(&storage, config, &args, rest),
+            ["exercise", "import", files @ ..] => exercise_import(&storage, config, &args, files),
+            ["workout", "log", rest @ ..] => workout_log(&storage, config, &args, rest),
+            ["workout", "show"] => workout_show(&storage, config, &args),
+            ["workout", "records"] => workout_records(&storage, config, &args),
+            ["profile", "show"] => profile_show(&storage, config, &args),
+            ["profile", "set", field, value] => profile_set(&storage, config, &args, field, value),
+            ["goal", "show"] => goal_show(&storage, config, &args),
+            ["goal", "set", target, rate] => goal_set(&storage, config, &args, target, rate),
+            ["goal", "clear"] => goal_clear(&storage, config, &args),
+            ["units", "show"] => units_show(&storage, config, &args),
+            ["units", "set", values @ ..] if !values.is_empty() => units_set(&storage, config, &args, values),
+            ["report"] => report(&storage, config, &args),
+            ["migrate"] => migrate(config, &args),
+            ["help"] | [] => Ok(Output { text: USAGE.to_string(), json: Json::string(USAGE) }),
+            _ => Err(CliError::Usage(format!("unknown command '{}'", command.join(" ")))),
+        }?;
+        Ok((output, args.flag("json")))
+    });
+    // e.g. that a damaged file was restored from its backup
+    for warning in storage.take_warnings() {
+        eprintln!("{}", warning);
+    }
+
+    match result {
+        Ok((output, json)) => {
+            if json {
+                println!("{}", output.json);
+            } else {
+                println!("{}", output.text);
+            }
+            EXIT_OK
+        },
+        Err(CliError::Usage(message)) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            EXIT_USAGE
+        },
+        Err(CliError::Failure(message)) => {
+            eprintln!("error: {}", message);
+            EXIT_FAILURE
+        },
+    }
+}
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+// --time, or the current time to the minute
+fn time_or_now(args: &Args) -> Result<NaiveTime, CliError> {
+    match args.option("time") {
+        Some(time) => NaiveTime::parse_from_str(time, "%H:%M")
+            .map_err(|_| CliError::Usage("--time must be HH:MM".to_string())),
+        None => {
+            let now = Local::now().time();
+            Ok(NaiveTime::from_hms_opt(now.hour(), now.minute(), 0).unwrap_or(now))
+        },
+    }
+}
+
+fn log_add(storage: &Rc<dyn Storage>, config: &Config, args: &Args, rest: &[&str]) -> Result<Output, CliError> {
+    let (food_id, amount) = match rest {
+        [food_id, amount @ ..] if !amount.is_empty() => (*food_id, amount.join(" ")),
+        _ => return Err(CliError::Usage("log add needs a food ID and an amount".to_string())),
+    };
+
+    let mut session = UserSession::open(storage, config, args.required("user")?)?;
+    let date = args.date("date")?.unwrap_or_else(today).format("%Y-%m-%d").to_string();
+
+    let food = session.food_database.get_food(food_id)
+        .ok_or_else(|| CliError::Failure(format!("no food with ID '{}'", food_id)))?
+        .clone();
+
+    let quantity = Quantity::parse(&amount)
+        .ok_or_else(|| CliError::Usage(format!("invalid amount '{}'", amount)))?;
+    let servings = quantity.to_servings(&food).map_err(CliError::Failure)?;
+
+    let time = time_or_now(args)?;
+    let meal = match args.option("meal") {
+        Some(meal) => Meal::from_str(meal).ok_or_else(|| CliError::Usage(format!("invalid meal '{}'", meal)))?,
+        None => Meal::from_time(time),
+    };
+
+    let command = session.daily_log.add_food(&date, &food, servings, meal.clone(), time, &SystemClock);
+    session.journal.record(command);
+    session.save()?;
+
+    let calories = (food.calories_per_serving as f64 * servings).round();
+    Ok(Output {
+        text: format!("Added {} serving(s) of {} to {} on {} ({}).",
+                      format_amount(servings), food.name, meal, date, session.units.energy(calories)),
+        json: Json::Object(vec![
+            ("date", Json::String(date)),
+            ("food_id", Json::string(&food.id)),
+            ("servings", Json::Number(servings)),
+            ("meal", Json::String(meal.to_string())),
+            ("time", Json::String(time.format("%H:%M").to_string())),
+            ("calories", Json::Number(calories)),
+        ]),
+    })
+}
+
+fn log_show(storage: &Rc<dyn Storage>, config: &Config, args: &Args) -> Result<Output, CliError> {
+    let session = UserSession::open(storage, config, args.required("user")?)?;
+    let date = args.date("date")?.unwrap_or_else(today).format("%Y-%m-%d").to_string();
+    let database = &session.food_database;
+    let units = &session.units;
+
+    let mut text = format!("Food Log for {}\n", date);
+    let mut entries_json = Vec::new();
+
+    for (i, entry) in session.daily_log.get_entries_for_date(&date).iter().enumerate() {
+        let name = database.get_food(&entry.food_id).map_or(entry.food_id.as_str(), |food| food.name.as_str());
+        let calories = entry.calories(database);
+        text.push_str(&format!("{}. {} {} | {} | {} | {}\n", i + 1, entry.time.format("%H:%M"),
+                               entry.meal, name, format_amount(entry.servings), units.energy(calories as f64)));
+        entries_json.push(Json::Object(vec![
+            ("index", Json::Number((i + 1) as f64)),
+            ("food_id", Json::string(&entry.food_id)),
+            ("name", Json::string(name)),
+            ("servings", Json::Number(entry.servings)),
+            ("meal", Json::String(entry.meal.to_string())),
+            ("time", Json::String(entry.time.format("%H:%M").to_string())),
+            ("calories", Json::Number(calories as f64)),
+            ("macros", Json::macros(&entry.macros(database))),
+        ]));
+    }
+
+    let consumed = session.daily_log.calculate_calories_for_date(&date, database);
+    let macros = session.daily_log.calculate_macros_for_date(&date, database);
+    text.push_str(&format!("Total Calories: {}\nTotal Macros: {}", units.energy(consumed as f64), macros));
+
+    let profile = session.profile_history.on(&date);
+    let mut exercises_json = Vec::new();
+    for (i, entry) in session.daily_log.get_exercises_for_date(&date).iter().enumerate() {
+        let name = session.exercise_database.get_exercise(&entry.exercise_id)
+            .map_or(entry.exercise_id.as_str(), |exercise| exercise.name.as_str());
+        let burned = profile.map_or(0, |profile| entry.calories(profile.weight));
+        text.push_str(&format!("\nExercise {}. {} | {} | {} min | {}", i + 1, entry.time.format("%H:%M"), name,
+                               format_amount(entry.minutes), units.energy(burned as f64)));
+        if let Some(details) = entry.details.as_ref().map(|details| details.describe(units)).filter(|d| !d.is_empty()) {
+            text.push_str(&format!(" | {}", details));
+        }
+        exercises_json.push(Json::Object(vec![
+            ("index", Json::Number((i + 1) as f64)),
+            ("exercise_id", Json::string(&entry.exercise_id)),
+            ("name", Json::string(name)),
+            ("minutes", Json::Number(entry.minutes)),
+            ("time", Json::String(entry.time.format("%H:%M").to_string())),
+            ("met", Json::Number(entry.met)),
+            ("calories_burned", Json::Number(burned as f64)),
+            ("activity", Json::activity(entry.details.as_ref())),
+        ]));
+    }
+    let burned = session.daily_log.calculate_burned_for_date(&date, &session.profile_history);
+
+    let target = profile.map(|profile| profile.get_target_calories());
+    if let Some(target) = target {
+        text.push_str(&format!("\nBurned Calories: {}\nTarget Calories: {}\nNet Calories (consumed - target - burned): {}",
+                               units.energy(burned as f64), units.energy(target as f64),
+                               units.energy(consumed as f64 - target as f64 - burned as f64)));
+    }
+
+    Ok(Output {
+        text,
+        json: Json::Object(vec![
+            ("date", Json::String(date)),
+            ("entries", Json::Array(entries_json)),
+            ("total_calories", Json::Number(consumed as f64)),
+            ("total_macros", Json::macros(&macros)),
+            ("exercises", Json::Array(exercises_json)),
+            ("burned_calories", Json::Number(burned as f64)),
+            ("target_calories", target.map_or(Json::Null, |t| Json::Number(t as f64))),
+            ("net_calories", target.map_or(Json::Null, |t| Json::Number(consumed as f64 - t as f64 - burned as f64))),
+        ]),
+    })
+}
+
+// The unit preferences of --user, or the configured defaults without one
+fn user_units(storage: &Rc<dyn Storage>, config: &Config, args: &Args) -> Result<UnitPreferences, CliError> {
+    let Some(user) = args.option("user") else {
+        return Ok(config.units);
+    };
+    let username = load_users(storage)?.find_user(user)
+        .ok_or_else(|| CliError::Failure(format!("no user named '{}'", user)))?;
+    storage.load_preferences(&username)
+        .map(|units| units.unwrap_or(config.units))
+        .map_err(|e| CliError::Failure(format!("could not load unit preferences: {}", e)))
+}
+
+fn food_search(storage: &Rc<dyn Storage>, config: &Config, args: &Args, keywords: &[&str]) -> Result<Output, CliError> {
+    let units = user_units(storage, config, args)?;
+    let database = load_food_database(storage.as_ref())?;
+    let keywords: Vec<String> = keywords.iter().map(|k| k.to_string()).collect();
+
+    let mut foods = database.get_foods_by_keywords(&keywords, args.flag("all"));
+    foods.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let text = if foods.is_empty() {
+        "No foods found matching your criteria.".to_string()
+    } else {
+        foods.iter()
+            .map(|food| format!("{} | {} ({})", food.id, food.name, food.describe_serving(&units)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    Ok(Output {
+        text,
+        json: Json::Array(foods.iter().map(|food| Json::food(food)).collect()),
+    })
+}
+
+fn food_add(storage: &Rc<dyn Storage>, config: &Config, args: &Args, rest: &[&str]) -> Result<Output, CliError> {
+    let (id, name, calories) = match rest {
+        [id, name, calories] => (*id, *name, *calories),
+        _ => return Err(CliError::Usage("food add needs an ID, a name and calories per serving".to_string())),
+    };
+
+    let calories = match user_units(storage, config, args)?.parse_energy(calories).map(|kcal| kcal.round() as u32) {
+        Some(n) if n > 0 => n,
+        _ => return Err(CliError::Usage(format!("invalid calories value '{}'", calories))),
+    };
+    if id.is_empty() || id.contains(char::is_whitespace) {
+        return Err(CliError::Usage("food ID must be non-empty and contain no spaces".to_string()));
+    }
+
+    let keywords: Vec<String> = args.option("keywords")
+        .map_or_else(Vec::new, |k| k.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect());
+
+    let macros = match args.option("macros") {
+        Some(m) => {
+            let fields: Vec<&str> = m.split(',').collect();
+            Macros::from_fields(&fields).ok_or_else(|| CliError::Usage(format!("invalid macros '{}'", m)))?
+        },
+        None => Macros::default(),
+    };
+
+    let serving = match args.option("serving") {
+        Some(s) => Some(ServingSize::parse(s).ok_or_else(|| CliError::Usage(format!("invalid serving size '{}'", s)))?),
+        None => None,
+    };
+
+    // With --user the addition goes into that user's undo history
+    let mut session = match args.option("user") {
+        Some(user) => Some(UserSession::open(storage, config, user)?),
+        None => None,
+    };
+    let mut database = load_food_database(storage.as_ref())?;
+
+    if database.get_food(id).is_some() {
+        return Err(CliError::Failure(format!("a food with ID '{}' already exists", id)));
+    }
+
+    let food = Food::new_basic(id, name, keywords, calories)
+        .with_macros(macros)
+        .with_serving(serving);
+    database.add_food(food.clone());
+    storage.save_foods(&database)
+        .map_err(|e| CliError::Failure(format!("could not save food database: {}", e)))?;
+
+    if let Some(session) = &mut session {
+        session.journal.record(CommandType::ChangeFoods(vec![(food.id.clone(), None, Some(food.clone()))]));
+        session.save()?;
+    }
+
+    Ok(Output {
+        text: format!("Food '{}' added successfully to the database.", name),
+        json: Json::food(&food),
+    })
+}
+
+fn exercise_search(storage: &Rc<dyn Storage>, args: &Args, keywords: &[&str]) -> Result<Output, CliError> {
+    let database = load_exercise_database(storage.as_ref())?;
+    let keywords: Vec<String> = keywords.iter().map(|k| k.to_string()).collect();
+    let exercises = database.get_exercises_by_keywords(&keywords, args.flag("all"));
+
+    let text = if exercises.is_empty() {
+        "No activities found matching your criteria.".to_string()
+    } else {
+        exercises.iter()
+            .map(|exercise| format!("{} | {} ({} MET)", exercise.id, exercise.name, format_amount(exercise.met)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    Ok(Output {
+        text,
+        json: Json::Array(exercises.iter().map(|exercise| Json::exercise(exercise)).collect()),
+    })
+}
+
+fn exercise_add(storage: &Rc<dyn Storage>, args: &Args, rest: &[&str]) -> Result<Output, CliError> {
+    let (id, name, met) = match rest {
+        [id, name, met] => (*id, *name, *met),
+        _ => return Err(CliError::Usage("exercise add needs an ID, a name and a MET value".to_string())),
+    };
+
+    let met = met.parse::<f64>().ok()
+        .filter(|met| *met > 0.0 && met.is_finite())
+        .ok_or_else(|| CliError::Usage(format!("invalid MET value '{}'", met)))?;
+    if id.is_empty() || id.contains(char::is_whitespace) {
+        return Err(CliError::Usage("activity ID must be non-empty and contain no spaces".to_string()));
+    }
+    let keywords: Vec<String> = args.option("keywords")
+        .map_or_else(Vec::new, |k| k.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect());
+
+    let mut database = load_exercise_database(storage.as_ref())?;
+    if database.get_exercise(id).is_some() {
+        return Err(CliError::Failure(format!("an activity with ID '{}' already exists", id)));
+    }
+
+    let exercise = Exercise { id: id.to_string(), name: name.to_string(), keywords, met };
+    database.add_exercise(exercise.clone());
+    storage.save_exercises(&database)
+        .map_err(|e| CliError::Failure(format!("could not save exercise database: {}", e)))?;
+
+    Ok(Output {
+        text: format!("Activity '{}' added to the exercise database.", name),
+        json: Json::exercise(&exercise),
+    })
+}
+
+fn exercise_log(storage: &Rc<dyn Storage>, config: &Config, args: &Args, rest: &[&str]) -> Result<Output, CliError> {
+    let (exercise_id, duration) = match rest {
+        [exercise_id, duration @ ..] if !duration.is_empty() => (*exercise_id, duration.join(" ")),
+        _ => return Err(CliError::Usage("exercise log needs an activity ID and a duration".to_string())),
+    };
+
+    let mut session = UserSession::open(storage, config, args.required("user")?)?;
+    let date = args.date("date")?.unwrap_or_else(today).format("%Y-%m-%d").to_string();
+
+    let exercise = session.exercise_database.get_exercise(exercise_id)
+        .ok_or_else(|| CliError::Failure(format!("no activity with ID '{}'", exercise_id)))?
+        .clone();
+    let minutes = parse_duration(&duration)
+        .ok_or_else(|| CliError::Usage(format!("invalid duration '{}'", duration)))?;
+    let time = time_or_now(args)?;
+    let weight = session.profile(&date)?.weight;
+
+    let command = session.daily_log.add_exercise(&date, &exercise, minutes, time, &SystemClock);
+    session.journal.record(command);
+    session.save()?;
+
+    let burned = calories_burned(exercise.met, weight, minutes);
+    Ok(Output {
+        text: format!("Logged {} min of {} on {} ({} burned).",
+                      format_amount(minutes), exercise.name, date, session.units.energy(burned as f64)),
+        json: Json::Object(vec![
+            ("date", Json::String(date)),
+            ("exercise_id", Json::string(&exercise.id)),
+            ("minutes", Json::Number(minutes)),
+            ("time", Json::String(time.format("%H:%M").to_string())),
+            ("met", Json::Number(exercise.met)),
+            ("calories_burned", Json::Number(burned as f64)),
+        ]),
+    })
+}
+
+fn exercise_import(storage: &Rc<dyn Storage>, config: &Config, args: &Args, files: &[&str]) -> Result<Output, CliError> {
+    if files.is_empty() {
+        return Err(CliError::Usage("exercise import needs at least one activity file".to_string()));
+    }
+
+    let mut session = UserSession::open(storage, config, args.required("user")?)?;
+    let chosen = match args.option("activity") {
+        Some(id) => Some(session.exercise_database.get_exercise(id)
+            .ok_or_else(|| CliError::Failure(format!("no activity with ID '{}'", id)))?
+            .clone()),
+        None => None,
+    };
+
+    // Every file is read before anything is logged, so a bad one doesn't leave the rest half imported
+    let mut activities = Vec::new();
+    for file in files {
+        activities.extend(import::read_file(Path::new(file))
+            .map_err(|e| CliError::Failure(format!("could not import {}: {}", file, e)))?);
+    }
+
+    let mut lines = Vec::new();
+    let mut imported_json = Vec::new();
+    let mut skipped_json = Vec::new();
+    for activity in &activities {
+        let sport = activity.sport.as_deref().unwrap_or("activity");
+        let date = local_date_of(activity.start);
+        if session.daily_log.has_imported(activity.start) {
+            lines.push(format!("Already imported: {} started {} {}.", sport, date, local_time_of(activity.start).format("%H:%M")));
+            skipped_json.push(Json::Object(vec![
+                ("date", Json::String(date)),
+                ("sport", activity.sport.as_deref().map_or(Json::Null, Json::string)),
+                ("start", Json::Number(activity.start as f64)),
+            ]));
+            continue;
+        }
+
+        let exercise = match &chosen {
+            Some(exercise) => exercise.clone(),
+            None => activity.sport.as_deref().and_then(import::exercise_for_sport)
+                .and_then(|id| session.exercise_database.get_exercise(id))
+                .ok_or_else(|| CliError::Failure(format!("no exercise matches the {} on {}; choose one with --activity", sport, date)))?
+                .clone(),
+        };
+        let entry = ExerciseEntry::imported(&exercise, activity, &SystemClock);
+        let weight = session.profile_history.on(&date).map(|profile| profile.weight);
+        lines.push(format!("Imported {} on {} at {}: {}.", exercise.name, date, entry.time.format("%H:%M"),
+                           entry.summary(&session.units, weight)));
+        imported_json.push(Json::Object(vec![
+            ("date", Json::String(date.clone())),
+            ("exercise_id", Json::string(&exercise.id)),
+            ("minutes", Json::Number(entry.minutes)),
+            ("time", Json::String(entry.time.format("%H:%M").to_string())),
+            ("calories_burned", weight.map_or(Json::Null, |weight| Json::Number(entry.calories(weight) as f64))),
+            ("activity", Json::activity(entry.details.as_ref())),
+        ]));
+
+        let command = session.daily_log.add_exercise_entry(&date, entry);
+        session.journal.record(command);
+    }
+    session.save()?;
+
+    Ok(Output {
+        text: lines.join("\n"),
+        json: Json::Object(vec![
+            ("imported", Json::Array(imported_json)),
+            ("skipped", Json::Array(skipped_json)),
+        ]),
+    })
+}
+
+fn workout_log(storage: &Rc<dyn Storage>, config: &Config, args: &Args, rest: &[&str]) -> Result<Output, CliError> {
+    let (lift, reps, weight) = match rest {
+        [lift, reps, weight @ ..] if !weight.is_empty() => (*lift, *reps, weight.join(" ")),
+        _ => return Err(CliError::Usage("workout log needs a lift, the reps and a weight".to_string())),
+    };
+
+    let mut session = UserSession::open(storage, config, args.required("user")?)?;
+    let date = args.date("date")?.unwrap_or_else(today).format("%Y-%m-%d").to_string();
+
+    let reps = reps.parse::<u32>().ok().filter(|reps| *reps > 0)
+        .ok_or_else(|| CliError::Usage(format!("invalid reps '{}'", reps)))?;
+    let weight = session.units.parse_weight(&weight).filter(|weight| *weight >= 0.0)
+        .ok_or_else(|| CliError::Usage(format!("invalid weight '{}'", weight)))?;
+    let rpe = match args.option("rpe") {
+        Some(rpe) => Some(parse_rpe(rpe).ok_or_else(|| CliError::Usage(format!("invalid RPE '{}' (expected 1 to 10)", rpe)))?),
+        None => None,
+    };
+    let time = time_or_now(args)?;
+    if lift.trim().is_empty() || lift.contains('\t') {
+        return Err(CliError::Usage("lift name must be non-empty and contain no tabs".to_string()));
+    }
+
+    // --muscles overrides those of a known lift
+    let muscles = match args.option("muscles") {
+        Some(muscles) => Some(MuscleGroup::parse_list(muscles)
+            .ok_or_else(|| CliError::Usage(format!("invalid muscle groups '{}'", muscles)))?),
+        None => None,
+    };
+    let (lift, muscles) = match (session.workout_log.find_lift(lift), muscles) {
+        (Some((name, _)), Some(muscles)) => (name, muscles),
+        (Some(known), None) => known,
+        (None, Some(muscles)) => (lift.trim().to_string(), muscles),
+        (None, None) => return Err(CliError::Usage(format!("'{}' is a new lift; give its muscle groups with --muscles", lift))),
+    };
+
+    let set = WorkoutSet::new(&lift, muscles, reps, weight, rpe, time, &SystemClock);
+    let records = session.workout_log.records_beaten_by(&set);
+    let json = Json::Object(vec![
+        ("date", Json::String(date.clone())),
+        ("set", Json::set(session.workout_log.get_sets_for_date(&date).len() + 1, &set)),
+        ("records", Json::Array(records.iter().map(|record| Json::string(record)).collect())),
+    ]);
+    let mut text = format!("Logged {} on {}: {} reps with {}.", lift, date, reps, describe_load(&session.units, weight));
+    if !records.is_empty() {
+        text.push_str(&format!("\nNew personal record: {}!", records.join(" and ")));
+    }
+
+    let command = session.workout_log.add_set(&date, set);
+    session.journal.record(command);
+    session.save()?;
+
+    Ok(Output { text, json })
+}
+
+fn workout_show(storage: &Rc<dyn Storage>, config: &Config, args: &Args) -> Result<Output, CliError> {
+    let session = UserSession::open(storage, config, args.required("user")?)?;
+    let date = args.date("date")?.unwrap_or_else(today);
+    let date_string = date.format("%Y-%m-%d").to_string();
+    let units = &session.units;
+
+    let sets = session.workout_log.get_sets_for_date(&date_string);
+    let mut text = format!("Workout for {}\n", date_string);
+    for (i, set) in sets.iter().enumerate() {
+        let rpe = set.rpe.map_or(String::new(), |rpe| format!(" @ RPE {}", format_amount(rpe)));
+        text.push_str(&format!("{}. {} | {} | {} × {}{}\n", i + 1, set.time.format("%H:%M"), set.lift,
+                               describe_load(units, set.weight), set.reps, rpe));
+    }
+    let volume: f64 = sets.iter().map(|set| set.volume()).sum();
+    text.push_str(&format!("Total Volume: {}\n", units.weight(volume)));
+
+    let start = WorkoutLog::week_start(date);
+    let weekly = session.workout_log.weekly_volume(date);
+    text.push_str(&format!("Week of {}:", start));
+    for (muscle, volume) in &weekly {
+        text.push_str(&format!("\n{} | {} sets | {}", muscle, volume.sets, units.weight(volume.volume)));
+    }
+    if weekly.is_empty() {
+        text.push_str(" no sets");
+    }
+
+    Ok(Output {
+        text,
+        json: Json::Object(vec![
+            ("date", Json::String(date_string)),
+            ("sets", Json::Array(sets.iter().enumerate().map(|(i, set)| Json::set(i + 1, set)).collect())),
+            ("total_volume", Json::Number(volume)),
+            ("week_start", Json::String(start.format("%Y-%m-%d").to_string())),
+            ("weekly_volume", Json::Array(weekly.iter().map(|(muscle, volume)| Json::Object(vec![
+                ("muscle", Json::String(muscle.to_string())),
+                ("sets", Json::Number(volume.sets as f64)),
+                ("volume", Json::Number(volume.volume)),
+            ])).collect())),
+        ]),
+    })
+}
+
+fn workout_records(storage: &Rc<dyn Storage>, config: &Config, args: &Args) -> Result<Output, CliError> {
+    let session = UserSession::open(storage, config, args.required("user")?)?;
+    let units = &session.units;
+
+    let records = session.workout_log.personal_records();
+    let text = if records.is_empty() {
+        "No sets logged yet.".to_string()
+    } else {
+        records.iter()
+            .map(|record| {
+                let (date, heaviest) = record.heaviest;
+                let (best_date, best) = record.best_1rm;
+                let mut line = format!("{} | heaviest {} × {} on {}", record.lift, describe_load(units, heaviest.weight),
+                                       heaviest.reps, date);
+                if best.weight > 0.0 {
+                    line.push_str(&format!(" | est. 1RM {} on {}", units.weight(best.estimated_1rm()), best_date));
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    Ok(Output {
+        text,
+        json: Json::Array(records.iter().map(|record| Json::Object(vec![
+            ("lift", Json::string(record.lift)),
+            ("heaviest", Json::Object(vec![
+                ("date", Json::string(record.heaviest.0)),
+                ("weight", Json::Number(record.heaviest.1.weight)),
+                ("reps", Json::Number(record.heaviest.1.reps as f64)),
+            ])),
+            ("best_estimated_1rm", Json::Object(vec![
+                ("date", Json::string(record.best_1rm.0)),
+                ("estimated_1rm", Json::Number(record.best_1rm.1.estimated_1rm())),
+            ])),
+        ])).collect()),
+    })
+}
+
+fn profile_show(storage: &Rc<dyn Storage>, config: &Config, args: &Args) -> Result<Output, CliError> {
+    let session = UserSession::open(storage, config, args.required("user")?)?;
+    let date = args.date("date")?.unwrap_or_else(today).format("%Y-%m-%d").to_string();
+    let profile = session.profile(&date)?;
+    let units = &session.units;
+
+    let mut text = format!("Profile on {}\nUsername: {}\nGender: {:?}\nHeight: {}\nAge: {}\nWeight: {}\nActivity Level: {:?}",
+                           date, profile.username, profile.gender, units.height(profile.height), profile.age,
+                           units.weight(profile.weight), profile.activity_level);
+    for (name, value) in [("Waist", profile.body.waist), ("Hip", profile.body.hip), ("Neck", profile.body.neck)] {
+        if let Some(value) = value {
+            text.push_str(&format!("\n{}: {}", name, units.length(value)));
+        }
+    }
+    if let Some(composition) = profile.describe_body_composition(units) {
+        text.push_str(&format!("\n{}", composition));
+    }
+    text.push_str(&format!("\nCalculation Method: {}\nTarget Calories: {}",
+                           profile.calculation_method_name(), units.energy(profile.get_target_calories() as f64)));
+
+    Ok(Output { text, json: Json::profile(profile) })
+}
+
+fn profile_set(storage: &Rc<dyn Storage>, config: &Config, args: &Args, field: &str, value: &str) -> Result<Output, CliError> {
+    let mut session = UserSession::open(storage, config, args.required("user")?)?;
+    let date = args.date("date")?.unwrap_or_else(today).format("%Y-%m-%d").to_string();
+    let mut profile = session.profile(&date)?.clone();
+    let unchanged = profile.clone();
+
+    let units = session.units;
+    let invalid = || CliError::Usage(format!("invalid {} '{}'", field, value));
+    let length = |value: &str| units.parse_length(value);
+    match field {
+        "weight" => profile.weight = units.parse_weight(value).filter(|w| *w > 0.0).ok_or_else(invalid)?,
+        "height" => profile.height = units.parse_length(value).filter(|h| *h > 0.0).ok_or_else(invalid)?,
+        "age" => profile.age = value.parse::<u32>().map_err(|_| invalid())?,
+        "gender" => profile.gender = Gender::from_str(value).ok_or_else(invalid)?,
+        "activity" => profile.activity_level = ActivityLevel::from_str(value).ok_or_else(invalid)?,
+        "body-fat" => {
+            let percentage = |value: &str| value.parse::<f64>().ok().filter(|bf| *bf <= 100.0);
+            profile.body.body_fat = parse_measurement(value, percentage).ok_or_else(invalid)?
+        },
+        "waist" => profile.body.waist = parse_measurement(value, length).ok_or_else(invalid)?,
+        "hip" => profile.body.hip = parse_measurement(value, length).ok_or_else(invalid)?,
+        "neck" => profile.body.neck = parse_measurement(value, length).ok_or_else(invalid)?,
+        "method" => match CalculatorRegistry::global().get(value) {
+            Some(calculator) => profile.set_calculation_method(calculator.id()),
+            None => return Err(CliError::Usage(format!("unknown calculation method '{}' (available: {})",
+                                                       value, CalculatorRegistry::global().ids()))),
+        },
+        _ => return Err(CliError::Usage(format!("unknown profile field '{}'", field))),
+    }
+
+    if profile != unchanged {
+        session.record_profile_change(&date, profile);
+    }
+    session.save()?;
+
+    let profile = session.profile(&date)?;
+    Ok(Output {
+        text: format!("Profile updated. Target Calories: {}", units.energy(profile.get_target_calories() as f64)),
+        json: Json::profile(profile),
+    })
+}
+
+// A positive amount read with `parse`, or "none" to clear the measurement
+fn parse_measurement(value: &str, parse: impl Fn(&str) -> Option<f64>) -> Option<Option<f64>> {
+    if value == "none" {
+        return Some(None);
+    }
+    parse(value).filter(|v| *v > 0.0).map(Some)
+}
+
+fn goal_output(session: &UserSession, date: &str) -> Result<Output, CliError> {
+    let profile = session.profile(date)?;
+    let units = &session.units;
+    let Some(goal) = &profile.goal else {
+        return Ok(Output {
+            text: format!("No weight goal on {}. Target Calories: {}", date, units.energy(profile.get_target_calories() as f64)),
+            json: Json::Object(vec![
+                ("date", Json::string(date)),
+                ("goal", Json::Null),
+                ("target_calories", Json::Number(profile.get_target_calories() as f64)),
+            ]),
+        });
+    };
+
+    let database = &session.food_database;
+    let projection = session.daily_log.project_goal(date, &session.profile_history, database);
+    let mut text = format!("Goal on {}: {}\nMaintenance Calories: {}\nTarget Calories: {}",
+                           date, goal.describe(profile.weight, units), units.energy(profile.maintenance_calories() as f64),
+                           units.energy(profile.get_target_calories() as f64));
+    if let Some(projection) = &projection {
+        text.push_str(&format!("\nProjected goal date: {}", projection.describe(units, &Locale::default())));
+    }
+
+    let GoalProjection { planned, average_balance, from_intake } = projection.unwrap_or(
+        GoalProjection { planned: None, average_balance: None, from_intake: None });
+    Ok(Output {
+        text,
+        json: Json::Object(vec![
+            ("date", Json::string(date)),
+            ("goal", Json::goal(goal)),
+            ("weight_kg", Json::Number(profile.weight)),
+            ("maintenance_calories", Json::Number(profile.maintenance_calories() as f64)),
+            ("target_calories", Json::Number(profile.get_target_calories() as f64)),
+            ("projected_date", Json::date(planned)),
+            ("average_balance", average_balance.map_or(Json::Null, Json::Number)),
+            ("projected_date_from_intake", Json::date(from_intake)),
+        ]),
+    })
+}
+
+fn goal_show(storage: &Rc<dyn Storage>, config: &Config, args: &Args) -> Result<Output, CliError> {
+    let session = UserSession::open(storage, config, args.required("user")?)?;
+    let date = args.date("date")?.unwrap_or_else(today).format("%Y-%m-%d").to_string();
+    goal_output(&session, &date)
+}
+
+fn goal_set(storage: &Rc<dyn Storage>, config: &Config, args: &Args, target: &str, rate: &str) -> Result<Output, CliError> {
+    let mut session = UserSession::open(storage, config, args.required("user")?)?;
+    let units = session.units;
+
+    let target_weight = units.parse_weight(target).filter(|w| *w > 0.0)
+        .ok_or_else(|| CliError::Usage(format!("invalid target weight '{}'", target)))?;
+    let weekly_rate = units.parse_weight(rate).filter(|r| WeightGoal::is_valid_rate(*r))
+        .ok_or_else(|| CliError::Usage(format!("invalid weekly change '{}'; it must be between 0 and {}",
+                                               rate, units.weight(WeightGoal::MAX_WEEKLY_RATE))))?;
+    let min_calories = match args.option("min-calories") {
+        Some(min) => Some(units.parse_energy(min).map(|kcal| kcal.round() as u32)
+            .ok_or_else(|| CliError::Usage(format!("invalid --min-calories '{}'", min)))?),
+        None => None,
+    };
+
+    let date = args.date("date")?.unwrap_or_else(today).format("%Y-%m-%d").to_string();
+    let mut profile = session.profile(&date)?.clone();
+    let goal = Some(WeightGoal { target_weight, weekly_rate, min_calories });
+    if profile.goal != goal {
+        profile.goal = goal;
+        session.record_profile_change(&date, profile);
+    }
+    session.save()?;
+    goal_output(&session, &date)
+}
+
+fn goal_clear(storage: &Rc<dyn Storage>, config: &Config, args: &Args) -> Result<Output, CliError> {
+    let mut session = UserSession::open(storage, config, args.required("user")?)?;
+    let date = args.date("date")?.unwrap_or_else(today).format("%Y-%m-%d").to_string();
+    let mut profile = session.profile(&date)?.clone();
+    if profile.goal.is_some() {
+        profile.goal = None;
+        session.record_profile_change(&date, profile);
+    }
+    session.save()?;
+    goal_output(&session, &date)
+}
+
+fn units_output(units: &UnitPreferences) -> Output {
+    Output {
+        text: format!("Units: {}\nEnergy: {}", units.system, units.energy),
+        json: Json::Object(vec![
+            ("units", Json::String(units.system.to_string())),
+            ("energy", Json::String(units.energy.to_string())),
+        ]),
+    }
+}
+
+fn units_show(storage: &Rc<dyn Storage>, config: &Config, args: &Args) -> Result<Output, CliError> {
+    let session = UserSession::open(storage, config, args.required("user")?)?;
+    Ok(units_output(&session.units))
+}
+
+fn units_set(storage: &Rc<dyn Storage>, config: &Config, args: &Args, values: &[&str]) -> Result<Output, CliError> {
+    let session = UserSession::open(storage, config, args.required("user")?)?;
+    let mut units = session.units;
+    for value in values {
+        if let Some(system) = UnitSystem::from_str(value) {
+            units.system = system;
+        } else if let Some(energy) = EnergyUnit::from_str(value) {
+            units.energy = energy;
+        } else {
+            return Err(CliError::Usage(format!("unknown unit '{}' (expected metric, imperial, kcal or kj)", value)));
+        }
+    }
+
+    storage.save_preferences(&session.username, &units)
+        .map_err(|e| CliError::Failure(format!("could not save unit preferences: {}", e)))?;
+    Ok(units_output(&units))
+}
+
+fn report(storage: &Rc<dyn Storage>, config: &Config, args: &Args) -> Result<Output, CliError> {
+    let session = UserSession::open(storage, config, args.required("user")?)?;
+    let to = args.date("to")?.unwrap_or_else(today);
+    let from = args.date("from")?.unwrap_or(to - Duration::days(6));
+    if from > to {
+        return Err(CliError::Usage("--from must not be after --to".to_string()));
+    }
+
+    let database = &session.food_database;
+    let units = &session.units;
+
+    let mut text = format!("Report for {} to {} (in {})\nDate | Consumed | Burned | Target | Net\n", from, to, units.energy);
+    let mut days = Vec::new();
+    let mut total_consumed = 0u64;
+    let mut day_count = 0u64;
+
+    let mut date = from;
+    while date <= to {
+        let date_string = date.format("%Y-%m-%d").to_string();
+        let target = session.profile(&date_string)?.get_target_calories();
+        let consumed = session.daily_log.calculate_calories_for_date(&date_string, database);
+        let burned = session.daily_log.calculate_burned_for_date(&date_string, &session.profile_history);
+        let diff = consumed as i64 - target as i64;
+        let net = diff - burned as i64;
+
+        text.push_str(&format!("{} | {:.0} | {:.0} | {:.0} | {:.0}\n", date_string, units.energy_value(consumed as f64),
+                               units.energy_value(burned as f64), units.energy_value(target as f64),
+                               units.energy_value(net as f64)));
+        days.push(Json::Object(vec![
+            ("date", Json::String(date_string.clone())),
+            ("consumed_calories", Json::Number(consumed as f64)),
+            ("burned_calories", Json::Number(burned as f64)),
+            ("target_calories", Json::Number(target as f64)),
+            ("difference", Json::Number(diff as f64)),
+            ("net_calories", Json::Number(net as f64)),
+            ("macros", Json::macros(&session.daily_log.calculate_macros_for_date(&date_string, database))),
+        ]));
+
+        total_consumed += consumed as u64;
+        day_count += 1;
+        date += Duration::days(1);
+    }
+
+    let average = total_consumed as f64 / day_count as f64;
+    text.push_str(&format!("Average consumed: {}/day", units.energy(average)));
+
+    Ok(Output {
+        text,
+        json: Json::Object(vec![
+            ("from", Json::String(from.format("%Y-%m-%d").to_string())),
+            ("to", Json::String(to.format("%Y-%m-%d").to_string())),
+            ("days", Json::Array(days)),
+            ("average_consumed_calories", Json::Number(average)),
+        ]),
+    })
+}
+
+fn migrate(config: &Config, args: &Args) -> Result<Output, CliError> {
+    let kind = |name: &str| -> Result<StorageKind, CliError> {
+        let value = args.required(name)?;
+        StorageKind::from_str(value)
+            .ok_or_else(|| CliError::Usage(format!("unknown storage '{}' (expected text or sqlite)", value)))
+    };
+    let (from_kind, to_kind) = (kind("from")?, kind("to")?);
+    if from_kind == to_kind {
+        return Err(CliError::Usage("--from and --to must be different storages".to_string()));
+    }
+
+    let open = |kind: StorageKind| kind.open(&config.data_dir)
+        .map_err(|e| CliError::Failure(format!("could not open {} storage: {}", kind, e)));
+    let (from, to) = (open(from_kind)?, open(to_kind)?);
+
+    // Don't silently overwrite data that already exists in the destination
+    let existing_users = to.load_users()
+        .map_err(|e| CliError::Failure(format!("could not read {} storage: {}", to_kind, e)))?;
+    if !args.flag("force") && (!existing_users.is_empty() || to.has_foods()) {
+        return Err(CliError::Failure(format!("the {} storage already has data; use --force to replace it", to_kind)));
+    }
+
+    let (summary, food_problem) = storage::migrate(from.as_ref(), to.as_ref())?;
+    for warning in from.take_warnings().into_iter().chain(to.take_warnings()) {
+        eprintln!("{}", warning);
+    }
+    if let Some(problem) = food_problem {
+        eprintln!("warning: {}", problem);
+    }
+
+    let mut text = format!("Copied {} users, {} foods, {} activities, {} log entries, {} exercise entries and {} workout sets \
+                            from {} to {} storage.",
+                           summary.users, summary.foods, summary.exercises, summary.log_entries, summary.exercise_entries,
+                           summary.workout_sets, from_kind, to_kind);
+    if !summary.skipped_users.is_empty() {
+        text.push_str(&format!("\nCopied without their data (invalid usernames): {}", summary.skipped_users.join(", ")));
+    }
+
+    Ok(Output {
+        text,
+        json: Json::Object(vec![
+            ("from", Json::String(from_kind.to_string())),
+            ("to", Json::String(to_kind.to_string())),
+            ("users", Json::Number(summary.users as f64)),
+            ("foods", Json::Number(summary.foods as f64)),
+            ("exercises", Json::Number(summary.exercises as f64)),
+            ("log_entries", Json::Number(summary.log_entries as f64)),
+            ("exercise_entries", Json::Number(summary.exercise_entries as f64)),
+            ("workout_sets", Json::Number(summary.workout_sets as f64)),
+            ("skipped_users", Json::Array(summary.skipped_users.iter().map(|name| Json::string(name)).collect())),
+        ]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn arguments_split_into_positionals_options_and_flags() {
+        let Ok(parsed) = Args::parse(&args(&["log", "add", "--food", "Rice, \"brown\"", "--json"])) else {
+            panic!("valid arguments were refused");
+        };
+        assert_eq!(parsed.positional, ["log", "add"]);
+        assert_eq!(parsed.option("food"), Some("Rice, \"brown\""));
+        assert!(parsed.flag("json"));
+    }
+
+    #[test]
+    fn users_from_a_newer_version_are_refused() {
+        let dir = std::env::temp_dir().join(format!("yada-cli-{}-newer-users", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let storage = StorageKind::Text.open(&dir).unwrap();
+        std::fs::write(dir.join("users.txt"), "# YADA users format 9\nalice,something new\n").unwrap();
+
+        match UserSession::open(&storage, &Config::new(&dir), "alice") {
+            Err(CliError::Failure(message)) => assert!(message.starts_with("could not load users: written by a newer version"), "{}", message),
+            _ => panic!("users from a newer version were accepted"),
+        }
+    }
+
+    #[test]
+    fn line_breaks_are_refused() {
+        for value in ["Fish\nChips", "Fish\r\nChips", "Fish\r"] {
+            match Args::parse(&args(&["foods", "add", "--name", value])) {
+                Err(CliError::Usage(message)) => assert!(message.contains("line breaks"), "{}", message),
+                _ => panic!("{:?} was accepted", value),
+            }
+        }
+    }
+}
@@ -0,0 +1,16 @@
+// The current date and time, behind a trait so tests can fix them
+
+use chrono::{DateTime, Local};
+
+pub trait Clock {
+    fn now(&self) -> DateTime<Local>;
+}
+
+// The system clock in the local time zone
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
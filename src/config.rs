@@ -0,0 +1,430 @@
+// Settings for the whole program, as opposed to one user's. Each setting is taken from,
+// in increasing order of precedence: its default, the config file, a YADA_* environment
+// variable and a command-line option:
+//
+//   setting    variable       option       value
+//   data-dir   YADA_DATA_DIR  --data-dir   where the data is kept
+//   storage    YADA_STORAGE   --storage    text or sqlite
+//   method     YADA_METHOD    --method     calculation method of new profiles, e.g. mifflin-st-jeor
+//   units      YADA_UNITS     --units      metric or imperial, for users who haven't chosen
+//   energy     YADA_ENERGY    --energy     kcal or kj, likewise
+//   locale     YADA_LOCALE    --locale     how the menu shows and reads dates, e.g. en-US or iso
+//   autosave   YADA_AUTOSAVE  --autosave   on to save after every change made in the menu
+//
+// The config file has one `setting = value` per line and `#` comment lines. It is
+// $XDG_CONFIG_HOME/yada/yada.conf (by default ~/.config/yada/yada.conf), or the file
+// named by YADA_CONFIG or --config. A relative data-dir in it is relative to the file.
+//
+// Every path the program uses is resolved here. The data directory defaults to
+// $XDG_DATA_HOME/yada (~/.local/share/yada); until that exists, a `data` directory in
+// the current directory, where earlier versions kept everything, is used instead.
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+
+use crate::CalculatorRegistry;
+use crate::storage::StorageKind;
+use crate::units::{EnergyUnit, UnitPreferences, UnitSystem};
+
+const SETTINGS: [&str; 7] = ["data-dir", "storage", "method", "units", "energy", "locale", "autosave"];
+
+// The order of day, month and year in dates
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum DateOrder {
+    #[default]
+    Iso,       // 2025-04-08
+    MonthDay,  // 04/08/2025
+    DaySlash,  // 08/04/2025
+    DayDot,    // 08.04.2025
+}
+
+// A locale such as en-US. Only the date format depends on it so far.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Locale {
+    name: String,
+    dates: DateOrder,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale { name: "iso".to_string(), dates: DateOrder::Iso }
+    }
+}
+
+impl Locale {
+    // Accepts `language[-REGION]`, also in the POSIX form `en_US.UTF-8`, and `iso` for
+    // ISO 8601 dates
+    pub(crate) fn from_str(s: &str) -> Option<Self> {
+        let name = s.split(['.', '@']).next().unwrap_or("");
+        let mut parts = name.split(['-', '_']);
+        let language = parts.next().unwrap_or("").to_lowercase();
+        let region = parts.next().map(|region| region.to_uppercase());
+        if parts.next().is_some() {
+            return None;
+        }
+
+        let dates = match (language.as_str(), region.as_deref()) {
+            ("iso" | "c" | "posix", None) => DateOrder::Iso,
+            (language, _) if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic()) => return None,
+            (_, Some(region)) if !(2..=3).contains(&region.len()) || !region.chars().all(|c| c.is_ascii_alphanumeric()) => return None,
+            (_, Some("US" | "PH")) => DateOrder::MonthDay,
+            ("ja" | "zh" | "ko" | "sv" | "lt" | "hu", _) => DateOrder::Iso,
+            ("de" | "da" | "fi" | "nb" | "nn" | "no" | "pl" | "cs" | "sk" | "ru" | "uk" | "tr" | "ro" | "et" | "lv", _) => DateOrder::DayDot,
+            _ => DateOrder::DaySlash,
+        };
+        Some(Locale { name: s.to_string(), dates })
+    }
+
+    fn date_format(&self) -> &'static str {
+        match self.dates {
+            DateOrder::Iso => "%Y-%m-%d",
+            DateOrder::MonthDay => "%m/%d/%Y",
+            DateOrder::DaySlash => "%d/%m/%Y",
+            DateOrder::DayDot => "%d.%m.%Y",
+        }
+    }
+
+    // How to type a date, for prompts
+    pub(crate) fn date_hint(&self) -> &'static str {
+        match self.dates {
+            DateOrder::Iso => "YYYY-MM-DD",
+            DateOrder::MonthDay => "MM/DD/YYYY",
+            DateOrder::DaySlash => "DD/MM/YYYY",
+            DateOrder::DayDot => "DD.MM.YYYY",
+        }
+    }
+
+    pub(crate) fn format_date(&self, date: NaiveDate) -> String {
+        date.format(self.date_format()).to_string()
+    }
+
+    // A date in this locale's format, or in YYYY-MM-DD which is always understood
+    pub(crate) fn parse_date(&self, input: &str) -> Option<NaiveDate> {
+        let input = input.trim();
+        NaiveDate::parse_from_str(input, self.date_format()).ok()
+            .or_else(|| NaiveDate::parse_from_str(input, "%Y-%m-%d").ok())
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+pub struct Config {
+    pub(crate) data_dir: PathBuf,
+    pub(crate) storage: StorageKind,
+    pub(crate) method: String,
+    pub(crate) units: UnitPreferences,
+    pub(crate) locale: Locale,
+    pub(crate) autosave: bool,
+}
+
+impl Config {
+    // The default settings with the data kept in `data_dir`
+    pub fn new(data_dir: impl Into<PathBuf>) -> Self {
+        Config {
+            data_dir: data_dir.into(),
+            storage: StorageKind::Text,
+            method: CalculatorRegistry::DEFAULT.to_string(),
+            units: UnitPreferences::default(),
+            locale: Locale::default(),
+            autosave: false,
+        }
+    }
+
+    // The settings from the config file, the environment and the options in `args`. The
+    // options (and --config) are removed from `args`, leaving the subcommand if any.
+    pub fn load(args: &mut Vec<String>) -> Result<Self, String> {
+        Config::load_from(args, |variable| env::var(variable).ok())
+    }
+
+    // Like `load`, with the environment variables looked up in `env` instead of the
+    // environment of the process
+    pub fn load_from(args: &mut Vec<String>, env: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let env = Env(&env);
+        let options = take_options(args)?;
+        let mut config = Config::new(env.default_data_dir());
+
+        match options.get("config").map(PathBuf::from).or_else(|| env.value("YADA_CONFIG").map(PathBuf::from)) {
+            Some(path) => config.read_file(&path, &env)?,
+            None => {
+                if let Some(path) = env.default_config_file() && path.exists() {
+                    config.read_file(&path, &env)?;
+                }
+            },
+        }
+
+        for setting in SETTINGS {
+            let variable = env_variable(setting);
+            if let Some(value) = env.value(&variable) {
+                config.set_in(setting, &value, &env).map_err(|e| format!("{}: {}", variable, e))?;
+            }
+        }
+        for setting in SETTINGS {
+            if let Some(value) = options.get(setting) {
+                config.set_in(setting, value, &env).map_err(|e| format!("--{}: {}", setting, e))?;
+            }
+        }
+
+        Ok(config)
+    }
+
+    // Changes one setting, e.g. `config.set("units", "imperial")`
+    pub fn set(&mut self, setting: &str, value: &str) -> Result<(), String> {
+        self.set_in(setting, value, &Env(&|variable| env::var(variable).ok()))
+    }
+
+    // `set` with `~` in a data-dir expanded to the home directory in `env`
+    fn set_in(&mut self, setting: &str, value: &str, env: &Env) -> Result<(), String> {
+        match setting {
+            "data-dir" if value.is_empty() => return Err("the data directory can't be empty".to_string()),
+            "data-dir" => self.data_dir = env.expand_home(value),
+            "storage" => {
+                self.storage = StorageKind::from_str(value)
+                    .ok_or_else(|| format!("unknown storage '{}' (expected text or sqlite)", value))?;
+            },
+            "method" => {
+                let registry = CalculatorRegistry::global();
+                let calculator = registry.get(value)
+                    .ok_or_else(|| format!("unknown calculation method '{}' (available: {})", value, registry.ids()))?;
+                self.method = calculator.id().to_string();
+            },
+            "units" => {
+                self.units.system = UnitSystem::from_str(value)
+                    .ok_or_else(|| format!("unknown units '{}' (expected metric or imperial)", value))?;
+            },
+            "energy" => {
+                self.units.energy = EnergyUnit::from_str(value)
+                    .ok_or_else(|| format!("unknown energy unit '{}' (expected kcal or kj)", value))?;
+            },
+            "locale" => {
+                self.locale = Locale::from_str(value)
+                    .ok_or_else(|| format!("invalid locale '{}' (expected e.g. en-US, de-DE or iso)", value))?;
+            },
+            "autosave" => {
+                self.autosave = match value.to_lowercase().as_str() {
+                    "on" | "yes" | "true" | "1" => true,
+                    "off" | "no" | "false" | "0" => false,
+                    _ => return Err(format!("invalid autosave '{}' (expected on or off)", value)),
+                };
+            },
+            _ => return Err(format!("unknown setting '{}' (expected one of {})", setting, SETTINGS.join(", "))),
+        }
+        Ok(())
+    }
+
+    fn read_file(&mut self, path: &Path, env: &Env) -> Result<(), String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("could not read config file {}: {}", path.display(), e))?;
+        let base = path.parent().unwrap_or(Path::new(""));
+
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let at_line = |e: String| format!("{} line {}: {}", path.display(), i + 1, e);
+
+            let (setting, value) = line.split_once('=')
+                .ok_or_else(|| at_line("expected `setting = value`".to_string()))?;
+            let (setting, value) = (setting.trim(), value.trim());
+            self.set_in(setting, value, env).map_err(at_line)?;
+            if setting == "data-dir" && self.data_dir.is_relative() {
+                self.data_dir = base.join(&self.data_dir);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Removes `--<setting> <value>` and `--config <file>` from `args`
+fn take_options(args: &mut Vec<String>) -> Result<HashMap<&'static str, String>, String> {
+    let mut options = HashMap::new();
+    let mut i = 0;
+    while i < args.len() {
+        let name = args[i].strip_prefix("--").and_then(|name| {
+            SETTINGS.iter().chain(&["config"]).find(|setting| **setting == name).copied()
+        });
+        let Some(name) = name else {
+            i += 1;
+            continue;
+        };
+        if i + 1 >= args.len() {
+            return Err(format!("--{} needs a value", name));
+        }
+        let value = args.remove(i + 1);
+        args.remove(i);
+        options.insert(name, value);
+    }
+    Ok(options)
+}
+
+// YADA_DATA_DIR for data-dir and so on
+fn env_variable(setting: &str) -> String {
+    format!("YADA_{}", setting.to_uppercase().replace('-', "_"))
+}
+
+// Where environment variables are looked up: the process's environment, or in tests a
+// made-up one
+struct Env<'a>(&'a dyn Fn(&str) -> Option<String>);
+
+impl Env<'_> {
+    // The value of an environment variable that is set and not empty
+    fn value(&self, variable: &str) -> Option<String> {
+        (self.0)(variable).filter(|value| !value.is_empty())
+    }
+
+    fn home_dir(&self) -> Option<PathBuf> {
+        self.value("HOME").or_else(|| self.value("USERPROFILE")).map(PathBuf::from)
+    }
+
+    // `~/...` under the home directory
+    fn expand_home(&self, path: &str) -> PathBuf {
+        match (path.strip_prefix("~/"), self.home_dir()) {
+            (Some(rest), Some(home)) => home.join(rest),
+            _ if path == "~" => self.home_dir().unwrap_or_else(|| PathBuf::from(path)),
+            _ => PathBuf::from(path),
+        }
+    }
+
+    // $<variable>/yada, or <fallback>/yada in the home directory. The XDG base directory
+    // spec says relative values are to be ignored.
+    fn xdg_dir(&self, variable: &str, fallback: &str) -> Option<PathBuf> {
+        self.value(variable).map(PathBuf::from).filter(|dir| dir.is_absolute())
+            .or_else(|| self.home_dir().map(|home| home.join(fallback)))
+            .map(|dir| dir.join("yada"))
+    }
+
+    fn default_config_file(&self) -> Option<PathBuf> {
+        self.xdg_dir("XDG_CONFIG_HOME", ".config").map(|dir| dir.join("yada.conf"))
+    }
+
+    fn default_data_dir(&self) -> PathBuf {
+        let legacy = PathBuf::from("data");
+        match self.xdg_dir("XDG_DATA_HOME", ".local/share") {
+            Some(dir) if dir.exists() || !legacy.is_dir() => dir,
+            _ => legacy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A directory of its own for each test
+    fn test_dir(test: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("yada-config-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    // An environment with only `variables` set
+    fn env_of<'a>(variables: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        |variable| variables.iter().find(|(name, _)| *name == variable).map(|(_, value)| value.to_string())
+    }
+
+    #[test]
+    fn without_settings_everything_is_the_default() {
+        let dir = test_dir("defaults");
+        // Otherwise a `data` directory in the current directory would be used
+        fs::create_dir_all(dir.join(".local/share/yada")).unwrap();
+        let home = dir.to_str().unwrap();
+        let mut args = args(&["report"]);
+        let config = Config::load_from(&mut args, env_of(&[("HOME", home)])).unwrap();
+        assert_eq!(args, ["report"]);
+        assert_eq!(config.data_dir, dir.join(".local/share/yada"));
+        assert_eq!(config.storage, StorageKind::Text);
+        assert_eq!(config.method, CalculatorRegistry::DEFAULT);
+        assert_eq!(config.units, UnitPreferences::default());
+        assert_eq!(config.locale, Locale::default());
+        assert!(!config.autosave);
+    }
+
+    #[test]
+    fn options_beat_variables_which_beat_the_file() {
+        let dir = test_dir("precedence");
+        let file = dir.join("yada.conf");
+        fs::write(&file, "# all from the file\nunits = imperial\nenergy = kj\nlocale = en-GB\nautosave = on\n").unwrap();
+
+        let variables = [("YADA_CONFIG", file.to_str().unwrap()), ("YADA_ENERGY", "kcal"), ("YADA_LOCALE", "de-DE")];
+        let mut args = args(&["--locale", "en-US", "log", "show"]);
+        let config = Config::load_from(&mut args, env_of(&variables)).unwrap();
+        assert_eq!(args, ["log", "show"]);
+        assert_eq!(config.units.system, UnitSystem::Imperial); // file
+        assert_eq!(config.units.energy, EnergyUnit::Kcal); // variable over file
+        assert_eq!(config.locale.to_string(), "en-US"); // option over variable and file
+        assert!(config.autosave); // file over default
+        assert_eq!(config.storage, StorageKind::Text); // default
+    }
+
+    #[test]
+    fn the_config_file_is_found_in_xdg_config_home() {
+        let dir = test_dir("xdg");
+        fs::create_dir_all(dir.join("config/yada")).unwrap();
+        fs::write(dir.join("config/yada/yada.conf"), "data-dir = here\nstorage = sqlite\n").unwrap();
+
+        let config_home = dir.join("config");
+        let config = Config::load_from(&mut Vec::new(), env_of(&[("XDG_CONFIG_HOME", config_home.to_str().unwrap())])).unwrap();
+        // A relative data-dir is relative to the file
+        assert_eq!(config.data_dir, dir.join("config/yada/here"));
+        assert_eq!(config.storage, StorageKind::Sqlite);
+    }
+
+    #[test]
+    fn the_config_option_beats_the_variable() {
+        let dir = test_dir("config-option");
+        fs::write(dir.join("a.conf"), "units = imperial\n").unwrap();
+        fs::write(dir.join("b.conf"), "units = metric\n").unwrap();
+
+        let (a, b) = (dir.join("a.conf"), dir.join("b.conf"));
+        let mut args = args(&["--config", b.to_str().unwrap()]);
+        let config = Config::load_from(&mut args, env_of(&[("YADA_CONFIG", a.to_str().unwrap())])).unwrap();
+        assert_eq!(config.units.system, UnitSystem::Metric);
+    }
+
+    #[test]
+    fn the_data_dir_may_start_in_the_home_directory() {
+        let variables = [("HOME", "/home/alice"), ("YADA_DATA_DIR", "~/yada-data")];
+        let config = Config::load_from(&mut Vec::new(), env_of(&variables)).unwrap();
+        assert_eq!(config.data_dir, PathBuf::from("/home/alice/yada-data"));
+    }
+
+    #[test]
+    fn empty_variables_are_ignored() {
+        let variables = [("HOME", "/home/alice"), ("YADA_UNITS", ""), ("YADA_DATA_DIR", "")];
+        let config = Config::load_from(&mut Vec::new(), env_of(&variables)).unwrap();
+        assert_eq!(config.units.system, UnitSystem::Metric);
+    }
+
+    #[test]
+    fn errors_say_where_the_bad_value_came_from() {
+        let dir = test_dir("errors");
+        let file = dir.join("yada.conf");
+        fs::write(&file, "units = metric\nstorage = floppy\n").unwrap();
+        let file = file.to_str().unwrap();
+
+        let error = |options: &[&str], variables: &[(&str, &str)]| {
+            Config::load_from(&mut args(options), env_of(variables)).err().unwrap_or_default()
+        };
+        let message = error(&["--config", file], &[]);
+        assert!(message.ends_with("yada.conf line 2: unknown storage 'floppy' (expected text or sqlite)"), "{}", message);
+        let message = error(&[], &[("YADA_UNITS", "furlongs")]);
+        assert!(message.starts_with("YADA_UNITS: unknown units 'furlongs'"), "{}", message);
+        let message = error(&["--units", "furlongs"], &[]);
+        assert!(message.starts_with("--units: unknown units 'furlongs'"), "{}", message);
+        assert_eq!(error(&["--locale"], &[]), "--locale needs a value");
+    }
+}
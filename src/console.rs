@@ -0,0 +1,49 @@
+// Where the interactive menu reads its input and writes its output. The program uses
+// the terminal; tests drive the application with a scripted console instead.
+//
+// Output is written with `write!`/`writeln!`, e.g. `writeln!(self.console, "Saved.")`.
+// Both methods take `&self` like `io::Stdin::read_line`, so a prompt can be shown while
+// the application is borrowed elsewhere.
+
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+pub trait Console {
+    // The next line of input without its line ending, or None at the end of input
+    fn read_line(&self) -> Option<String>;
+
+    fn write_str(&self, text: &str);
+
+    fn write_fmt(&self, args: fmt::Arguments) {
+        match args.as_str() {
+            Some(text) => self.write_str(text),
+            None => self.write_str(&args.to_string()),
+        }
+    }
+}
+
+// Standard input and output
+pub struct StdConsole;
+
+impl Console for StdConsole {
+    fn read_line(&self) -> Option<String> {
+        let mut line = String::new();
+        match io::stdin().lock().read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                let trimmed = line.trim_end_matches(['\n', '\r']).len();
+                line.truncate(trimmed);
+                Some(line)
+            },
+            // Input that can't be read (e.g. invalid UTF-8) ends the session like EOF
+            Err(_) => None,
+        }
+    }
+
+    fn write_str(&self, text: &str) {
+        // Output that can't be written (e.g. a closed pipe) is dropped
+        let mut stdout = io::stdout().lock();
+        let _ = stdout.write_all(text.as_bytes());
+        let _ = stdout.flush();
+    }
+}
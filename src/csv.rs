@@ -0,0 +1,130 @@
+// Quoting for the comma-separated records in the data files, following RFC 4180: a field
+// that contains the delimiter or a double quote is written in double quotes with any
+// quotes inside doubled, e.g. `basic,rice,"Rice, brown",...`. Lists inside a field
+// (keywords, components) use the same rules with '|' as the delimiter.
+//
+// Unlike RFC 4180, line breaks inside fields are not supported: the data files are read
+// line by line, so a record has to be one line. Values never contain them (the command
+// line refuses them and the menu turns them into spaces). A field with one would be
+// quoted, but the record would still be cut in two when the file is read back.
+//
+// Files written before quoting existed never quote, so a quote only opens a quoted field
+// at the very start of a field. Anywhere else it is read literally, as it always was.
+
+use std::borrow::Cow;
+
+fn needs_quotes(field: &str, delimiter: char) -> bool {
+    field.contains([delimiter, '"', '\n', '\r'])
+}
+
+pub(crate) fn quote(field: &str, delimiter: char) -> Cow<'_, str> {
+    if needs_quotes(field, delimiter) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+// Joins fields into one record, quoting those that need it
+pub(crate) fn join<S: AsRef<str>>(fields: &[S], delimiter: char) -> String {
+    let quoted: Vec<Cow<str>> = fields.iter().map(|field| quote(field.as_ref(), delimiter)).collect();
+    quoted.join(&delimiter.to_string())
+}
+
+// Splits a record into its (unquoted) fields. An empty record is a single empty field.
+pub(crate) fn split(record: &str, delimiter: char) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut chars = record.chars().peekable();
+
+    loop {
+        let mut field = String::new();
+
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    },
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err(format!("field {} has no closing quote", fields.len() + 1)),
+                }
+            }
+
+            match chars.next() {
+                None => {
+                    fields.push(field);
+                    return Ok(fields);
+                },
+                Some(c) if c == delimiter => fields.push(field),
+                Some(c) => return Err(format!("unexpected '{}' after the closing quote of field {}", c, fields.len() + 1)),
+            }
+            continue;
+        }
+
+        loop {
+            match chars.next() {
+                Some(c) if c == delimiter => {
+                    fields.push(field);
+                    break;
+                },
+                Some(c) => field.push(c),
+                None => {
+                    fields.push(field);
+                    return Ok(fields);
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_round_trip() {
+        let fields = ["basic", "rice", "Rice, brown", "say \"cheese\"", "", "\"", "a|b"];
+        let record = join(&fields, ',');
+        assert_eq!(record, r#"basic,rice,"Rice, brown","say ""cheese""",,"""",a|b"#);
+        assert_eq!(split(&record, ','), Ok(fields.map(String::from).to_vec()));
+    }
+
+    #[test]
+    fn lists_inside_a_field_round_trip() {
+        let keywords = ["fish", "chips | peas", "\"big\""];
+        let list = join(&keywords, '|');
+        assert_eq!(list, r#"fish|"chips | peas"|"""big""""#);
+        let record = join(&["chips", list.as_str()], ',');
+        let fields = split(&record, ',').unwrap();
+        assert_eq!(split(&fields[1], '|'), Ok(keywords.map(String::from).to_vec()));
+    }
+
+    #[test]
+    fn line_breaks_dont_survive_a_file() {
+        for line_break in ["\n", "\r\n"] {
+            let record = join(&["soup", &format!("Hot{}Soup", line_break), "120"], ',');
+            assert_eq!(record, format!("soup,\"Hot{}Soup\",120", line_break));
+
+            // The data files are read a line at a time, which cuts the quoted field off
+            let lines: Vec<&str> = record.lines().collect();
+            assert_eq!(lines, ["soup,\"Hot", "Soup\",120"]);
+            assert_eq!(split(lines[0], ','), Err("field 2 has no closing quote".to_string()));
+            assert_eq!(split(lines[1], ','), Ok(vec!["Soup\"".to_string(), "120".to_string()]));
+        }
+    }
+
+    #[test]
+    fn old_unquoted_records_are_read_literally() {
+        assert_eq!(split(r#"rice,5" pan,"#, ','), Ok(vec!["rice".to_string(), "5\" pan".to_string(), String::new()]));
+        assert_eq!(split("", ','), Ok(vec![String::new()]));
+    }
+
+    #[test]
+    fn bad_quoting_is_an_error() {
+        assert_eq!(split(r#"a,"b"#, ','), Err("field 2 has no closing quote".to_string()));
+        assert_eq!(split(r#""a"b,c"#, ','), Err("unexpected 'b' after the closing quote of field 1".to_string()));
+    }
+}
@@ -0,0 +1,340 @@
+// Crash-safe reading and writing of the line-based text data files.
+//
+// A file is never rewritten in place: the new contents go to `<file>.tmp`, which is
+// flushed to disk and then renamed over the original, so a crash leaves either the old
+// or the new version. The version being replaced is kept as `<file>.bak`.
+//
+// Every file starts with a header naming its kind and format version, e.g.
+// `# YADA foods format 2`. Files from before headers existed are version 1. Older files
+// are upgraded line by line when they are read, keeping the original as `<file>.v<N>`;
+// files from a newer version are refused with ErrorKind::Unsupported and left untouched.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// `<file>.<suffix>` next to `path`, e.g. data/foods.txt.bak
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+// Makes a finished rename durable
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+// Writes `contents` to `tmp` and makes sure it reached the disk
+fn write_synced(tmp: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = File::create(tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+// Upgrades one line of a file from the version its index stands for to the next
+pub(crate) type Migration = fn(&str) -> Result<String, String>;
+
+// The kind and current version of a data file, with the migrations that bring older
+// versions up to date: `migrations[0]` upgrades version 1 lines to version 2, and so on.
+pub(crate) struct Format {
+    pub(crate) name: &'static str,
+    pub(crate) version: u32,
+    pub(crate) migrations: &'static [Migration],
+}
+
+impl Format {
+    fn header(&self) -> String {
+        format!("# YADA {} format {}", self.name, self.version)
+    }
+
+    // The version in the header of `content`, or 1 when there is no header
+    fn version_of(&self, content: &str) -> io::Result<u32> {
+        let first_line = content.lines().next().unwrap_or("");
+        let Some(header) = first_line.strip_prefix("# YADA ") else {
+            return Ok(1);
+        };
+
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("invalid file header '{}'", first_line));
+        let (name, version) = header.split_once(" format ").ok_or_else(invalid)?;
+        let version = version.trim().parse::<u32>().ok().filter(|v| *v > 0).ok_or_else(invalid)?;
+        if name != self.name {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      format!("expected a {} file, found a {} file", self.name, name)));
+        }
+        Ok(version)
+    }
+
+    // `content` in the current version. Lines that can't be migrated are kept as they are,
+    // so they are reported as damaged when parsed.
+    fn upgrade(&self, content: &str) -> io::Result<(String, u32)> {
+        let version = self.version_of(content)?;
+        if version > self.version {
+            return Err(io::Error::new(io::ErrorKind::Unsupported,
+                                      format!("written by a newer version of YADA (format {}, this version reads up to {})",
+                                              version, self.version)));
+        }
+        if version == self.version {
+            return Ok((content.to_string(), version));
+        }
+
+        let mut upgraded = self.header();
+        // Files that started at version 1 with a header have one too
+        let body = content.lines().skip(usize::from(content.starts_with("# YADA ")));
+        for line in body {
+            let mut line = line.to_string();
+            if !line.trim().is_empty() {
+                for migration in &self.migrations[version as usize - 1..] {
+                    match migration(&line) {
+                        Ok(migrated) => line = migrated,
+                        Err(_) => break,
+                    }
+                }
+            }
+            upgraded.push('\n');
+            upgraded.push_str(&line);
+        }
+        // Keep a missing newline at the end, which shows the last line was cut off
+        if content.ends_with('\n') || content.is_empty() {
+            upgraded.push('\n');
+        }
+        Ok((upgraded, version))
+    }
+}
+
+// Replaces the file at `path` with `contents`, keeping the previous version as `.bak`
+fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let tmp = sibling(path, "tmp");
+    write_synced(&tmp, contents.as_bytes())?;
+
+    if path.exists() {
+        // A hard link keeps the old version without a moment where `path` is missing
+        let backup = sibling(path, "bak");
+        if backup.exists() {
+            fs::remove_file(&backup)?;
+        }
+        if fs::hard_link(path, &backup).is_err() {
+            fs::copy(path, &backup)?;
+        }
+    }
+
+    fs::rename(&tmp, path)?;
+    sync_dir(path)
+}
+
+// Writes the lines in `contents` under the header of `format`
+pub(crate) fn write_lines(path: &Path, format: &Format, contents: &str) -> io::Result<()> {
+    write_atomic(path, &format!("{}\n{}", format.header(), contents))
+}
+
+// Parses every non-empty line, returning the parsed values and the (1-based) numbers of
+// the lines that didn't parse with the reason. A last line without its newline was cut
+// off mid-write.
+fn parse_lines<T>(content: &str, parse: &mut impl FnMut(&str) -> Result<T, String>) -> (Vec<T>, Vec<(usize, String)>) {
+    let mut items = Vec::new();
+    let mut bad_lines = Vec::new();
+    let line_count = content.lines().count();
+
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() || (i == 0 && line.starts_with("# YADA ")) {
+            continue;
+        }
+        let truncated = i + 1 == line_count && !content.ends_with('\n');
+        match parse(line) {
+            Ok(_) if truncated => bad_lines.push((i + 1, "line is cut off".to_string())),
+            Ok(item) => items.push(item),
+            Err(e) => bad_lines.push((i + 1, e)),
+        }
+    }
+
+    (items, bad_lines)
+}
+
+fn describe_lines(lines: &[(usize, String)]) -> String {
+    let shown: Vec<String> = lines.iter().take(5).map(|(n, e)| format!("line {}: {}", n, e)).collect();
+    let more = if lines.len() > 5 { format!("; and {} more", lines.len() - 5) } else { String::new() };
+    format!("{}{}", shown.join("; "), more)
+}
+
+// Reads a data file of the given format line by line with `parse`, upgrading it first if
+// it is from an older version. If any line is damaged, the file is copied to `.corrupt`
+// and the `.bak` from the previous save is used instead when it is intact (and restored in
+// place of the damaged file). Without a usable backup the intact lines are returned.
+// Either way a warning says what happened. The warnings, including one for an upgrade,
+// are returned with the values for the caller to show.
+pub(crate) fn read_lines<T>(path: &Path, format: &Format, mut parse: impl FnMut(&str) -> Result<T, String>) -> io::Result<(Vec<T>, Vec<String>)> {
+    let mut warnings = Vec::new();
+    let original = fs::read_to_string(path)?;
+    let (content, version) = format.upgrade(&original)?;
+    if version < format.version {
+        let old = sibling(path, &format!("v{}", version));
+        fs::copy(path, &old)?;
+        write_atomic(path, &content)?;
+        warnings.push(format!("Upgraded {} from format {} to {}; the old file was kept as {}.",
+                              path.display(), version, format.version, old.display()));
+    }
+
+    let (items, bad_lines) = parse_lines(&content, &mut parse);
+    if bad_lines.is_empty() {
+        return Ok((items, warnings));
+    }
+
+    let corrupt = sibling(path, "corrupt");
+    fs::copy(path, &corrupt)?;
+
+    let backup = sibling(path, "bak");
+    if let Ok(Ok((backup_content, _))) = fs::read_to_string(&backup).map(|backup| format.upgrade(&backup)) {
+        let (backup_items, backup_bad_lines) = parse_lines(&backup_content, &mut parse);
+        if backup_bad_lines.is_empty() {
+            // Restore without going through write_atomic, which would replace the backup
+            let tmp = sibling(path, "tmp");
+            write_synced(&tmp, backup_content.as_bytes())?;
+            fs::rename(&tmp, path)?;
+            sync_dir(path)?;
+
+            warnings.push(format!("Warning: {} is damaged ({}). Restored the previous version from {}; changes from the last save are lost. The damaged file was kept as {}.",
+                                  path.display(), describe_lines(&bad_lines), backup.display(), corrupt.display()));
+            return Ok((backup_items, warnings));
+        }
+    }
+
+    warnings.push(format!("Warning: {} is damaged ({}) and has no usable backup. Those lines were skipped; the damaged file was kept as {}.",
+                          path.display(), describe_lines(&bad_lines), corrupt.display()));
+    Ok((items, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Version 1 lines are `name`, version 2 added `,count` and version 3 is upper case
+    const TEST_FORMAT: Format = Format {
+        name: "test",
+        version: 3,
+        migrations: &[add_count, upper_case],
+    };
+
+    fn add_count(line: &str) -> Result<String, String> {
+        if line.contains(',') { Err("already has a count".to_string()) } else { Ok(format!("{},1", line)) }
+    }
+
+    fn upper_case(line: &str) -> Result<String, String> {
+        Ok(line.to_uppercase())
+    }
+
+    fn parse(line: &str) -> Result<String, String> {
+        match line.split_once(',') {
+            Some((name, count)) if count.parse::<u32>().is_ok() => Ok(name.to_string()),
+            _ => Err(format!("bad line '{}'", line)),
+        }
+    }
+
+    // A file of its own for each test
+    fn test_file(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yada-datafile-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("test.txt")
+    }
+
+    #[test]
+    fn written_lines_read_back() {
+        let path = test_file("round-trip");
+        write_lines(&path, &TEST_FORMAT, "APPLE,2\nPEAR,1\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# YADA test format 3\nAPPLE,2\nPEAR,1\n");
+
+        let (names, warnings) = read_lines(&path, &TEST_FORMAT, parse).unwrap();
+        assert_eq!(names, ["APPLE", "PEAR"]);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        // The version replaced by a save is kept
+        write_lines(&path, &TEST_FORMAT, "PLUM,3\n").unwrap();
+        assert_eq!(fs::read_to_string(sibling(&path, "bak")).unwrap(), "# YADA test format 3\nAPPLE,2\nPEAR,1\n");
+    }
+
+    #[test]
+    fn version_1_files_are_upgraded_through_every_migration() {
+        let path = test_file("from-v1");
+        fs::write(&path, "apple\n\npear\n").unwrap();
+
+        let (names, warnings) = read_lines(&path, &TEST_FORMAT, parse).unwrap();
+        assert_eq!(names, ["APPLE", "PEAR"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# YADA test format 3\nAPPLE,1\n\nPEAR,1\n");
+        assert_eq!(fs::read_to_string(sibling(&path, "v1")).unwrap(), "apple\n\npear\n");
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("from format 1 to 3"), "{}", warnings[0]);
+
+        // Upgraded once only
+        let (_, warnings) = read_lines(&path, &TEST_FORMAT, parse).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+
+    #[test]
+    fn later_versions_skip_the_migrations_they_dont_need() {
+        let path = test_file("from-v2");
+        fs::write(&path, "# YADA test format 2\napple,4\n").unwrap();
+
+        let (names, _) = read_lines(&path, &TEST_FORMAT, parse).unwrap();
+        assert_eq!(names, ["APPLE"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# YADA test format 3\nAPPLE,4\n");
+        assert!(sibling(&path, "v2").exists());
+    }
+
+    #[test]
+    fn files_from_a_newer_version_are_refused_and_left_alone() {
+        let path = test_file("newer");
+        fs::write(&path, "# YADA test format 4\nsomething new\n").unwrap();
+
+        let error = read_lines(&path, &TEST_FORMAT, parse).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+        assert!(error.to_string().contains("format 4, this version reads up to 3"), "{}", error);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# YADA test format 4\nsomething new\n");
+        assert!(!sibling(&path, "bak").exists() && !sibling(&path, "corrupt").exists());
+    }
+
+    #[test]
+    fn files_of_another_kind_or_with_a_bad_header_are_refused() {
+        let path = test_file("headers");
+        for header in ["# YADA foods format 1", "# YADA test format 0", "# YADA test version 2"] {
+            fs::write(&path, format!("{}\napple,1\n", header)).unwrap();
+            let error = read_lines(&path, &TEST_FORMAT, parse).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{}", header);
+        }
+    }
+
+    #[test]
+    fn a_damaged_file_is_replaced_by_its_backup() {
+        let path = test_file("damaged");
+        write_lines(&path, &TEST_FORMAT, "APPLE,1\n").unwrap();
+        write_lines(&path, &TEST_FORMAT, "APPLE,1\nPEAR,2\n").unwrap();
+        fs::write(&path, "# YADA test format 3\nAPPLE,1\nPEAR\n").unwrap();
+
+        let (names, warnings) = read_lines(&path, &TEST_FORMAT, parse).unwrap();
+        assert_eq!(names, ["APPLE"]);
+        assert!(warnings[0].contains("is damaged (line 3: bad line 'PEAR'). Restored the previous version"), "{}", warnings[0]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# YADA test format 3\nAPPLE,1\n");
+        assert_eq!(fs::read_to_string(sibling(&path, "corrupt")).unwrap(), "# YADA test format 3\nAPPLE,1\nPEAR\n");
+    }
+
+    #[test]
+    fn without_a_backup_the_intact_lines_are_kept() {
+        let path = test_file("no-backup");
+        // The last line was cut off mid-write
+        fs::write(&path, "# YADA test format 3\nAPPLE,1\nPEAR,2").unwrap();
+
+        let (names, warnings) = read_lines(&path, &TEST_FORMAT, parse).unwrap();
+        assert_eq!(names, ["APPLE"]);
+        assert!(warnings[0].contains("(line 3: line is cut off) and has no usable backup"), "{}", warnings[0]);
+        assert!(sibling(&path, "corrupt").exists());
+    }
+}
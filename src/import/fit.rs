@@ -0,0 +1,321 @@
+// FIT, the binary format written by Garmin and most other devices. A file is a header
+// followed by records: definition messages describe the fields of a local message type,
+// and data messages of that type follow. Only the session summaries are needed, or the
+// individual records when a file has no session.
+
+use std::collections::HashMap;
+
+use super::{ImportedActivity, TrackPoint, summarize_track};
+
+// Seconds from the unix epoch to the FIT epoch, 1989-12-31T00:00:00Z
+const FIT_EPOCH: u64 = 631_065_600;
+
+const SPORT: u16 = 12;
+const SESSION: u16 = 18;
+const RECORD: u16 = 20;
+const TIMESTAMP: u8 = 253;
+
+struct Definition {
+    global: u16,
+    little_endian: bool,
+    fields: Vec<(u8, usize, u8)>, // field number, size in bytes, base type
+    developer_size: usize,        // bytes of developer fields, which are skipped
+}
+
+pub(super) fn is_fit(bytes: &[u8]) -> bool {
+    bytes.len() >= 12 && matches!(bytes[0], 12 | 14) && &bytes[8..12] == b".FIT"
+}
+
+fn take<'a>(bytes: &'a [u8], at: &mut usize, len: usize) -> Result<&'a [u8], String> {
+    let taken = bytes.get(*at..*at + len).ok_or("the FIT file is truncated")?;
+    *at += len;
+    Ok(taken)
+}
+
+// An unsigned integer or enum field, or None if it holds the invalid value or has a
+// type these messages don't use
+fn field_value(bytes: &[u8], base_type: u8, little_endian: bool) -> Option<u64> {
+    let (size, zero_invalid) = match base_type {
+        0x00 | 0x02 => (1, false),
+        0x0A => (1, true),
+        0x84 => (2, false),
+        0x8B => (2, true),
+        0x86 => (4, false),
+        0x8C => (4, true),
+        _ => return None,
+    };
+    if bytes.len() != size {
+        return None;
+    }
+    let value = if little_endian {
+        bytes.iter().rev().fold(0u64, |value, byte| value << 8 | u64::from(*byte))
+    } else {
+        bytes.iter().fold(0u64, |value, byte| value << 8 | u64::from(*byte))
+    };
+    let invalid = if zero_invalid { 0 } else { (1u64 << (size * 8)) - 1 };
+    (value != invalid).then_some(value)
+}
+
+fn sport_name(sport: u64) -> Option<String> {
+    let name = match sport {
+        1 => "running",
+        2 => "cycling",
+        5 => "swimming",
+        11 => "walking",
+        15 => "rowing",
+        17 => "hiking",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+fn session_activity(fields: &HashMap<u8, u64>) -> Option<ImportedActivity> {
+    let start = fields.get(&2)? + FIT_EPOCH;
+    let seconds = fields.get(&8).or(fields.get(&7)).map(|ms| *ms as f64 / 1000.0)?;
+    Some(ImportedActivity {
+        sport: fields.get(&5).copied().and_then(sport_name),
+        start,
+        seconds,
+        distance: fields.get(&9).map(|cm| *cm as f64 / 100.0),
+        ascent: fields.get(&22).map(|m| *m as f64),
+        heart_rate: fields.get(&16).map(|bpm| *bpm as u32),
+        calories: fields.get(&11).map(|kcal| *kcal as u32),
+    })
+}
+
+fn record_point(fields: &HashMap<u8, u64>) -> TrackPoint {
+    TrackPoint {
+        time: fields.get(&TIMESTAMP).map(|time| time + FIT_EPOCH),
+        position: None,
+        elevation: fields.get(&78).or(fields.get(&2)).map(|altitude| *altitude as f64 / 5.0 - 500.0),
+        distance: fields.get(&5).map(|cm| *cm as f64 / 100.0),
+        heart_rate: fields.get(&3).map(|bpm| *bpm as u32),
+    }
+}
+
+pub(super) fn parse(bytes: &[u8]) -> Result<Vec<ImportedActivity>, String> {
+    let header_size = usize::from(bytes[0]);
+    let data_size = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+    let data = bytes.get(header_size..header_size + data_size).ok_or("the FIT file is truncated")?;
+
+    let mut definitions: HashMap<u8, Definition> = HashMap::new();
+    let mut last_timestamp: u64 = 0;
+    let mut sport = None;
+    let mut sessions = Vec::new();
+    let mut points = Vec::new();
+    let mut at = 0;
+
+    while at < data.len() {
+        let header = take(data, &mut at, 1)?[0];
+
+        if header & 0x80 == 0 && header & 0x40 != 0 {
+            let has_developer_fields = header & 0x20 != 0;
+            let fixed = take(data, &mut at, 5)?;
+            let little_endian = fixed[1] == 0;
+            let global = if little_endian {
+                u16::from_le_bytes([fixed[2], fixed[3]])
+            } else {
+                u16::from_be_bytes([fixed[2], fixed[3]])
+            };
+            let fields = take(data, &mut at, usize::from(fixed[4]) * 3)?
+                .chunks(3)
+                .map(|field| (field[0], usize::from(field[1]), field[2]))
+                .collect();
+            let mut developer_size = 0;
+            if has_developer_fields {
+                let count = usize::from(take(data, &mut at, 1)?[0]);
+                developer_size = take(data, &mut at, count * 3)?.chunks(3).map(|field| usize::from(field[1])).sum();
+            }
+            definitions.insert(header & 0x0F, Definition { global, little_endian, fields, developer_size });
+            continue;
+        }
+
+        // A compressed timestamp header carries the low five bits of the time since the last one
+        let (local, compressed_time) = if header & 0x80 != 0 {
+            let offset = u64::from(header & 0x1F);
+            let mut time = (last_timestamp & !0x1F) + offset;
+            if offset < last_timestamp & 0x1F {
+                time += 0x20;
+            }
+            ((header >> 5) & 0x03, Some(time))
+        } else {
+            (header & 0x0F, None)
+        };
+        let definition = definitions.get(&local)
+            .ok_or_else(|| format!("the FIT file uses message type {} before defining it", local))?;
+
+        let mut fields = HashMap::new();
+        for &(number, size, base_type) in &definition.fields {
+            if let Some(value) = field_value(take(data, &mut at, size)?, base_type, definition.little_endian) {
+                fields.insert(number, value);
+            }
+        }
+        take(data, &mut at, definition.developer_size)?;
+
+        if let Some(time) = compressed_time {
+            fields.insert(TIMESTAMP, time);
+        }
+        if let Some(time) = fields.get(&TIMESTAMP) {
+            last_timestamp = *time;
+        }
+
+        match definition.global {
+            SPORT => sport = fields.get(&0).copied().and_then(sport_name),
+            SESSION => sessions.extend(session_activity(&fields)),
+            RECORD => points.push(record_point(&fields)),
+            _ => {},
+        }
+    }
+
+    if sessions.is_empty() {
+        return Ok(summarize_track(sport, &points).into_iter().collect());
+    }
+    Ok(sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2025-04-05T09:00:00Z in FIT time
+    const START: u32 = 1_743_843_600 - FIT_EPOCH as u32;
+
+    // A 14 byte header, the records and a (zero) CRC
+    fn file(records: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![14, 0x20, 0x34, 0x08];
+        bytes.extend((records.len() as u32).to_le_bytes());
+        bytes.extend(b".FIT");
+        bytes.extend([0, 0]);
+        bytes.extend(records);
+        bytes.extend([0, 0]);
+        bytes
+    }
+
+    // A definition message for `local` with (field number, size, base type) fields and
+    // developer fields of the given sizes
+    fn definition(local: u8, global: u16, little_endian: bool, fields: &[(u8, u8, u8)], developer: &[u8]) -> Vec<u8> {
+        let header = 0x40 | local | if developer.is_empty() { 0 } else { 0x20 };
+        let mut bytes = vec![header, 0, if little_endian { 0 } else { 1 }];
+        bytes.extend(if little_endian { global.to_le_bytes() } else { global.to_be_bytes() });
+        bytes.push(fields.len() as u8);
+        for (number, size, base_type) in fields {
+            bytes.extend([*number, *size, *base_type]);
+        }
+        if !developer.is_empty() {
+            bytes.push(developer.len() as u8);
+            for (i, size) in developer.iter().enumerate() {
+                bytes.extend([i as u8, *size, 0]);
+            }
+        }
+        bytes
+    }
+
+    fn session() -> Vec<u8> {
+        let fields = [(253, 4, 0x86), (2, 4, 0x86), (5, 1, 0x00), (7, 4, 0x86), (8, 4, 0x86), (9, 4, 0x86),
+                      (11, 2, 0x84), (16, 1, 0x02), (22, 2, 0x84)];
+        let mut records = definition(1, SESSION, true, &fields, &[]);
+        records.push(0x01);
+        records.extend((START + 2700).to_le_bytes());
+        records.extend(START.to_le_bytes());
+        records.push(17); // hiking
+        records.extend(2_800_000u32.to_le_bytes()); // elapsed, including a pause
+        records.extend(2_700_000u32.to_le_bytes()); // timer
+        records.extend(612_345u32.to_le_bytes());
+        records.extend(410u16.to_le_bytes());
+        records.push(0xFF); // no heart rate
+        records.extend(250u16.to_le_bytes());
+        records
+    }
+
+    #[test]
+    fn recognises_fit_files() {
+        assert!(is_fit(&file(&[])));
+        assert!(!is_fit(b"<gpx></gpx>"));
+        assert!(!is_fit(&[14, 0x20, 0, 0]));
+    }
+
+    #[test]
+    fn reads_the_session_summary() {
+        let activities = parse(&file(&session())).unwrap();
+        assert_eq!(activities, vec![ImportedActivity {
+            sport: Some("hiking".to_string()),
+            start: 1_743_843_600,
+            seconds: 2700.0,
+            distance: Some(6123.45),
+            ascent: Some(250.0),
+            heart_rate: None,
+            calories: Some(410),
+        }]);
+    }
+
+    #[test]
+    fn sums_up_the_records_without_a_session() {
+        let mut records = definition(0, SPORT, true, &[(0, 1, 0x00)], &[]);
+        records.extend([0x00, 5]);
+        records.extend(definition(1, RECORD, true, &[(253, 4, 0x86), (3, 1, 0x02), (5, 4, 0x86), (78, 4, 0x86)], &[]));
+        for (seconds, heart_rate, altitude) in [(0u32, 100u8, 10u32), (60, 110, 30), (120, 120, 20)] {
+            records.push(0x01);
+            records.extend((START + seconds).to_le_bytes());
+            records.push(heart_rate);
+            records.extend((seconds * 300).to_le_bytes()); // 3 m/s in cm
+            records.extend(((altitude + 500) * 5).to_le_bytes());
+        }
+
+        let activities = parse(&file(&records)).unwrap();
+        assert_eq!(activities.len(), 1);
+        let activity = &activities[0];
+        assert_eq!(activity.sport.as_deref(), Some("swimming"));
+        assert_eq!((activity.start, activity.seconds), (1_743_843_600, 120.0));
+        assert_eq!(activity.distance, Some(360.0));
+        assert_eq!(activity.ascent, Some(20.0));
+        assert_eq!(activity.heart_rate, Some(110));
+        assert_eq!(activity.calories, None);
+    }
+
+    #[test]
+    fn compressed_timestamps_count_from_the_last_full_one() {
+        let mut records = definition(0, RECORD, true, &[(253, 4, 0x86), (3, 1, 0x02)], &[]);
+        records.push(0x00);
+        records.extend((START + 20).to_le_bytes());
+        records.push(100);
+        records.extend(definition(1, RECORD, true, &[(3, 1, 0x02)], &[]));
+        // 10 seconds later, then past the 32 second rollover of the low five bits
+        for (seconds, heart_rate) in [(30u32, 110u8), (50, 120)] {
+            records.push(0x80 | (1 << 5) | ((START + seconds) & 0x1F) as u8);
+            records.push(heart_rate);
+        }
+
+        let activities = parse(&file(&records)).unwrap();
+        assert_eq!((activities[0].start, activities[0].seconds), (1_743_843_620, 30.0));
+        assert_eq!(activities[0].heart_rate, Some(110));
+    }
+
+    #[test]
+    fn reads_big_endian_messages_and_skips_developer_fields() {
+        let mut records = definition(2, RECORD, false, &[(253, 4, 0x86), (3, 1, 0x02)], &[2, 1]);
+        for (seconds, heart_rate) in [(0u32, 130u8), (90, 150)] {
+            records.push(0x02);
+            records.extend((START + seconds).to_be_bytes());
+            records.push(heart_rate);
+            records.extend([0xAA, 0xBB, 0xCC]);
+        }
+
+        let activities = parse(&file(&records)).unwrap();
+        assert_eq!((activities[0].start, activities[0].seconds), (1_743_843_600, 90.0));
+        assert_eq!(activities[0].heart_rate, Some(140));
+    }
+
+    #[test]
+    fn refuses_broken_files() {
+        let mut bytes = file(&session());
+        bytes.truncate(30);
+        assert_eq!(parse(&bytes), Err("the FIT file is truncated".to_string()));
+
+        // A message in the middle cut short, with a data size that still covers it
+        let mut records = session();
+        records.truncate(records.len() - 3);
+        assert_eq!(parse(&file(&records)), Err("the FIT file is truncated".to_string()));
+
+        assert!(parse(&file(&[0x03, 1, 2])).unwrap_err().contains("before defining it"));
+    }
+}
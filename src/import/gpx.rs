@@ -0,0 +1,34 @@
+// GPX: each <trk> is an activity. Its <type> names the sport, as written by Garmin and
+// Strava exports, and heart rates come from Garmin's TrackPointExtension (<gpxtpx:hr>).
+
+use super::xml::Element;
+use super::{ImportedActivity, TrackPoint, parse_time, summarize_track};
+
+fn track_point(point: &Element) -> TrackPoint {
+    let coordinate = |name: &str| point.attribute(name).and_then(|value| value.trim().parse::<f64>().ok());
+    let mut heart_rates = Vec::new();
+    point.descendants("hr", &mut heart_rates);
+
+    TrackPoint {
+        time: point.child_text("time").and_then(parse_time),
+        position: coordinate("lat").zip(coordinate("lon")),
+        elevation: point.child_text("ele").and_then(|ele| ele.parse::<f64>().ok()),
+        distance: None,
+        heart_rate: heart_rates.first().and_then(|hr| hr.text.trim().parse::<u32>().ok()),
+    }
+}
+
+pub(super) fn parse(root: &Element) -> Result<Vec<ImportedActivity>, String> {
+    let mut activities = Vec::new();
+    for track in root.children("trk") {
+        let sport = track.child_text("type").filter(|sport| !sport.is_empty()).map(|sport| sport.to_lowercase());
+        let mut points = Vec::new();
+        track.descendants("trkpt", &mut points);
+        let points: Vec<TrackPoint> = points.into_iter().map(track_point).collect();
+
+        if let Some(activity) = summarize_track(sport, &points) {
+            activities.push(activity);
+        }
+    }
+    Ok(activities)
+}
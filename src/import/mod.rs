@@ -0,0 +1,131 @@
+// Importing activities recorded by watches and bike computers. FIT files are the binary
+// format most devices write; GPX and TCX are the XML exports offered by most apps. Each
+// file becomes one activity per recorded session, which the caller logs as an exercise
+// entry attributed to the logged-in user.
+//
+// Times are unix timestamps in UTC. Distances and elevation gain are in metres.
+
+mod fit;
+mod gpx;
+mod tcx;
+mod xml;
+
+use std::fs;
+use std::path::Path;
+
+use chrono::DateTime;
+
+// One activity as recorded, before it is matched to the exercise database
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ImportedActivity {
+    pub(crate) sport: Option<String>, // lowercase, e.g. "running"; None if the file doesn't say
+    pub(crate) start: u64,
+    pub(crate) seconds: f64,
+    pub(crate) distance: Option<f64>,
+    pub(crate) ascent: Option<f64>,
+    pub(crate) heart_rate: Option<u32>, // average, in beats per minute
+    pub(crate) calories: Option<u32>,   // as estimated by the device
+}
+
+// Samples along a track, from which GPX files and TCX files without lap totals are summed up
+#[derive(Debug, Default)]
+struct TrackPoint {
+    time: Option<u64>,
+    position: Option<(f64, f64)>, // latitude, longitude in degrees
+    elevation: Option<f64>,
+    distance: Option<f64>, // distance covered so far, if the device recorded it
+    heart_rate: Option<u32>,
+}
+
+const EARTH_RADIUS: f64 = 6_371_000.0;
+
+// Great-circle distance in metres between two positions
+fn haversine((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let (d_lat, d_lon) = (lat2 - lat1, (lon2 - lon1).to_radians());
+    let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
+
+// An ISO 8601 time such as 2025-04-08T07:00:00Z
+fn parse_time(s: &str) -> Option<u64> {
+    DateTime::parse_from_rfc3339(s.trim()).ok().and_then(|time| u64::try_from(time.timestamp()).ok())
+}
+
+// Sums up a track: its duration from the first to the last timestamp, the distance
+// recorded by the device or else measured between positions, the elevation gained and
+// the average heart rate. None if it has fewer than two timed points.
+fn summarize_track(sport: Option<String>, points: &[TrackPoint]) -> Option<ImportedActivity> {
+    let times: Vec<u64> = points.iter().filter_map(|point| point.time).collect();
+    let (start, end) = (*times.iter().min()?, *times.iter().max()?);
+    if end <= start {
+        return None;
+    }
+
+    let recorded = points.iter().filter_map(|point| point.distance).reduce(f64::max);
+    let positions: Vec<(f64, f64)> = points.iter().filter_map(|point| point.position).collect();
+    let measured = (positions.len() > 1)
+        .then(|| positions.windows(2).map(|pair| haversine(pair[0], pair[1])).sum::<f64>().round());
+
+    let elevations: Vec<f64> = points.iter().filter_map(|point| point.elevation).collect();
+    let ascent = (elevations.len() > 1)
+        .then(|| elevations.windows(2).map(|pair| (pair[1] - pair[0]).max(0.0)).sum());
+
+    let heart_rates: Vec<u32> = points.iter().filter_map(|point| point.heart_rate).collect();
+    let heart_rate = (!heart_rates.is_empty())
+        .then(|| (heart_rates.iter().sum::<u32>() as f64 / heart_rates.len() as f64).round() as u32);
+
+    Some(ImportedActivity {
+        sport,
+        start,
+        seconds: (end - start) as f64,
+        distance: recorded.or(measured),
+        ascent,
+        heart_rate,
+        calories: None,
+    })
+}
+
+// The activities in a FIT, GPX or TCX file, told apart by their contents
+pub(crate) fn read_file(path: &Path) -> Result<Vec<ImportedActivity>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+
+    let activities = if fit::is_fit(&bytes) {
+        fit::parse(&bytes)?
+    } else {
+        let text = String::from_utf8(bytes).ok()
+            .filter(|text| text.trim_start_matches('\u{feff}').trim_start().starts_with('<'))
+            .ok_or("not a FIT, GPX or TCX file")?;
+        let root = xml::parse(&text).map_err(|e| format!("invalid XML: {}", e))?;
+        match root.name.as_str() {
+            "gpx" => gpx::parse(&root)?,
+            "TrainingCenterDatabase" => tcx::parse(&root)?,
+            name => return Err(format!("not a FIT, GPX or TCX file (the root element is <{}>)", name)),
+        }
+    };
+
+    if activities.is_empty() {
+        return Err("the file has no activities with a start and an end time".to_string());
+    }
+    Ok(activities)
+}
+
+// The exercise database entry for a recorded sport, if there is an obvious one
+pub(crate) fn exercise_for_sport(sport: &str) -> Option<&'static str> {
+    let sport = sport.to_lowercase().replace(['_', '-', ' '], "");
+    match sport.as_str() {
+        "running" | "run" | "trailrunning" | "treadmillrunning" | "treadmill" => Some("run"),
+        "cycling" | "biking" | "bike" | "ride" | "roadbiking" | "mountainbiking" | "indoorcycling" => Some("cycle"),
+        "swimming" | "swim" | "lapswimming" | "openwaterswimming" => Some("swim"),
+        "walking" | "walk" => Some("walk"),
+        "hiking" | "hike" => Some("hike"),
+        "rowing" | "indoorrowing" => Some("row"),
+        "elliptical" => Some("elliptical"),
+        "yoga" => Some("yoga"),
+        "tennis" => Some("tennis"),
+        "basketball" => Some("basketball"),
+        "soccer" => Some("soccer"),
+        "strengthtraining" | "training" => Some("weights"),
+        _ => None,
+    }
+}
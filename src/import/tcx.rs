@@ -0,0 +1,155 @@
+// TCX (Garmin Training Center): each <Activity> has a Sport attribute and laps with
+// their own totals of time, distance, calories and average heart rate. The trackpoints
+// inside the laps give the elevation gain, and stand in for totals a lap doesn't have.
+
+use super::xml::Element;
+use super::{ImportedActivity, TrackPoint, parse_time, summarize_track};
+
+fn track_point(point: &Element) -> TrackPoint {
+    let number = |element: Option<&Element>, name: &str| {
+        element.and_then(|element| element.child_text(name)).and_then(|value| value.parse::<f64>().ok())
+    };
+    let position = point.child("Position");
+
+    TrackPoint {
+        time: point.child_text("Time").and_then(parse_time),
+        position: number(position, "LatitudeDegrees").zip(number(position, "LongitudeDegrees")),
+        elevation: number(Some(point), "AltitudeMeters"),
+        distance: number(Some(point), "DistanceMeters"),
+        heart_rate: number(point.child("HeartRateBpm"), "Value").map(|hr| hr.round() as u32),
+    }
+}
+
+// The sum of a lap total over the laps that have it, or None if none do
+fn lap_total(laps: &[&Element], name: &str) -> Option<f64> {
+    let values: Vec<f64> = laps.iter()
+        .filter_map(|lap| lap.child_text(name))
+        .filter_map(|value| value.parse::<f64>().ok())
+        .collect();
+    (!values.is_empty()).then(|| values.iter().sum())
+}
+
+fn parse_activity(activity: &Element) -> Option<ImportedActivity> {
+    let sport = activity.attribute("Sport")
+        .filter(|sport| !sport.is_empty() && *sport != "Other")
+        .map(|sport| sport.to_lowercase());
+    let laps: Vec<&Element> = activity.children("Lap").collect();
+
+    let mut points = Vec::new();
+    activity.descendants("Trackpoint", &mut points);
+    let points: Vec<TrackPoint> = points.into_iter().map(track_point).collect();
+    let track = summarize_track(sport.clone(), &points);
+
+    let start = activity.child_text("Id").and_then(parse_time)
+        .or_else(|| laps.first().and_then(|lap| lap.attribute("StartTime")).and_then(parse_time))
+        .or(track.as_ref().map(|track| track.start))?;
+    let seconds = lap_total(&laps, "TotalTimeSeconds").filter(|seconds| *seconds > 0.0)
+        .or(track.as_ref().map(|track| track.seconds))?;
+
+    // Each lap's average heart rate weighted by its time
+    let timed_heart_rates: Vec<(f64, f64)> = laps.iter()
+        .filter_map(|lap| {
+            let heart_rate = lap.child("AverageHeartRateBpm")?.child_text("Value")?.parse::<f64>().ok()?;
+            let seconds = lap.child_text("TotalTimeSeconds")?.parse::<f64>().ok()?;
+            Some((heart_rate, seconds))
+        })
+        .collect();
+    let lap_time: f64 = timed_heart_rates.iter().map(|(_, seconds)| seconds).sum();
+    let heart_rate = if lap_time > 0.0 {
+        Some((timed_heart_rates.iter().map(|(heart_rate, seconds)| heart_rate * seconds).sum::<f64>() / lap_time).round() as u32)
+    } else {
+        track.as_ref().and_then(|track| track.heart_rate)
+    };
+
+    Some(ImportedActivity {
+        sport,
+        start,
+        seconds,
+        distance: lap_total(&laps, "DistanceMeters").or(track.as_ref().and_then(|track| track.distance)),
+        ascent: track.as_ref().and_then(|track| track.ascent),
+        heart_rate,
+        calories: lap_total(&laps, "Calories").map(|calories| calories.round() as u32),
+    })
+}
+
+pub(super) fn parse(root: &Element) -> Result<Vec<ImportedActivity>, String> {
+    let mut activities = Vec::new();
+    root.descendants("Activity", &mut activities);
+    Ok(activities.into_iter().filter_map(parse_activity).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::xml;
+
+    const RIDE: &str = r#"<?xml version="1.0"?>
+<TrainingCenterDatabase xmlns="http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2">
+  <Activities>
+    <Activity Sport="Biking">
+      <Id>2025-04-07T17:30:00Z</Id>
+      <Lap StartTime="2025-04-07T17:30:00Z">
+        <TotalTimeSeconds>1800</TotalTimeSeconds><DistanceMeters>10000</DistanceMeters><Calories>300</Calories>
+        <AverageHeartRateBpm><Value>130</Value></AverageHeartRateBpm>
+        <Track>
+          <Trackpoint><Time>2025-04-07T17:30:00Z</Time><AltitudeMeters>50</AltitudeMeters></Trackpoint>
+          <Trackpoint><Time>2025-04-07T17:59:00Z</Time><AltitudeMeters>80</AltitudeMeters></Trackpoint>
+        </Track>
+      </Lap>
+      <Lap StartTime="2025-04-07T18:00:00Z">
+        <TotalTimeSeconds>600</TotalTimeSeconds><DistanceMeters>4000</DistanceMeters><Calories>100</Calories>
+        <AverageHeartRateBpm><Value>150</Value></AverageHeartRateBpm>
+        <Track>
+          <Trackpoint><Time>2025-04-07T18:00:00Z</Time><AltitudeMeters>70</AltitudeMeters></Trackpoint>
+          <Trackpoint><Time>2025-04-07T18:10:00Z</Time><AltitudeMeters>90</AltitudeMeters></Trackpoint>
+        </Track>
+      </Lap>
+    </Activity>
+  </Activities>
+</TrainingCenterDatabase>"#;
+
+    fn activities(document: &str) -> Vec<ImportedActivity> {
+        parse(&xml::parse(document).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn adds_up_the_laps() {
+        assert_eq!(activities(RIDE), vec![ImportedActivity {
+            sport: Some("biking".to_string()),
+            start: 1_744_047_000,
+            seconds: 2400.0,
+            distance: Some(14000.0),
+            ascent: Some(50.0),
+            // (130 × 1800 + 150 × 600) / 2400
+            heart_rate: Some(135),
+            calories: Some(400),
+        }]);
+    }
+
+    #[test]
+    fn falls_back_to_the_trackpoints_without_lap_totals() {
+        let document = r#"<TrainingCenterDatabase><Activities><Activity Sport="Other"><Lap><Track>
+            <Trackpoint><Time>2025-04-07T06:00:00Z</Time><DistanceMeters>0</DistanceMeters>
+              <HeartRateBpm><Value>120</Value></HeartRateBpm></Trackpoint>
+            <Trackpoint><Time>2025-04-07T06:15:00Z</Time><DistanceMeters>2500</DistanceMeters>
+              <HeartRateBpm><Value>140</Value></HeartRateBpm></Trackpoint>
+            </Track></Lap></Activity></Activities></TrainingCenterDatabase>"#;
+
+        assert_eq!(activities(document), vec![ImportedActivity {
+            sport: None,
+            start: 1_744_005_600,
+            seconds: 900.0,
+            distance: Some(2500.0),
+            ascent: None,
+            heart_rate: Some(130),
+            calories: None,
+        }]);
+    }
+
+    #[test]
+    fn skips_activities_without_a_time() {
+        let document = r#"<TrainingCenterDatabase><Activities><Activity Sport="Running"><Lap>
+            <DistanceMeters>5000</DistanceMeters></Lap></Activity></Activities></TrainingCenterDatabase>"#;
+        assert_eq!(activities(document), Vec::new());
+    }
+}
@@ -0,0 +1,202 @@
+// Just enough XML for GPX and TCX files: elements, attributes, text, comments, CDATA and
+// the predefined and numeric entities. Namespace prefixes are dropped, so `gpxtpx:hr` is
+// found as `hr`. DTDs and processing instructions are skipped.
+
+#[derive(Debug, Default)]
+pub(crate) struct Element {
+    pub(crate) name: String,
+    pub(crate) attributes: Vec<(String, String)>,
+    pub(crate) children: Vec<Element>,
+    pub(crate) text: String,
+}
+
+impl Element {
+    pub(crate) fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    pub(crate) fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub(crate) fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    // Every element with this name below this one, in document order
+    pub(crate) fn descendants<'a>(&'a self, name: &str, found: &mut Vec<&'a Element>) {
+        for child in &self.children {
+            if child.name == name {
+                found.push(child);
+            }
+            child.descendants(name, found);
+        }
+    }
+
+    // The trimmed text of the child with this name
+    pub(crate) fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.trim())
+    }
+}
+
+// `gpxtpx:hr` -> `hr`
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn decode_entities(s: &str) -> Result<String, String> {
+    let mut decoded = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        let end = rest[start..].find(';').ok_or("unterminated entity")? + start;
+        let entity = &rest[start + 1..end];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity.strip_prefix('#').and_then(|decimal| decimal.parse::<u32>().ok()),
+                };
+                code.and_then(char::from_u32).ok_or_else(|| format!("unknown entity '&{};'", entity))?
+            },
+        };
+        decoded.push(c);
+        rest = &rest[end + 1..];
+    }
+    decoded.push_str(rest);
+    Ok(decoded)
+}
+
+// name="value" pairs inside a start tag
+fn parse_attributes(s: &str) -> Result<Vec<(String, String)>, String> {
+    let mut attributes = Vec::new();
+    let mut rest = s.trim();
+    while !rest.is_empty() {
+        let equals = rest.find('=').ok_or_else(|| format!("invalid attribute in '{}'", s))?;
+        let name = rest[..equals].trim();
+        rest = rest[equals + 1..].trim_start();
+        let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')
+            .ok_or_else(|| format!("unquoted attribute '{}'", name))?;
+        let end = rest[1..].find(quote).ok_or_else(|| format!("unterminated attribute '{}'", name))? + 1;
+        attributes.push((local_name(name).to_string(), decode_entities(&rest[1..end])?));
+        rest = rest[end + 1..].trim_start();
+    }
+    Ok(attributes)
+}
+
+// The root element of a document
+pub(crate) fn parse(document: &str) -> Result<Element, String> {
+    // Elements that are still open; the bottom one collects the root
+    let mut stack = vec![Element::default()];
+    let mut rest = document.strip_prefix('\u{feff}').unwrap_or(document);
+
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            stack.last_mut().ok_or("unbalanced tags")?.text.push_str(&decode_entities(rest)?);
+            break;
+        };
+        if start > 0 {
+            stack.last_mut().ok_or("unbalanced tags")?.text.push_str(&decode_entities(&rest[..start])?);
+        }
+        rest = &rest[start..];
+
+        let skip_to = |rest: &str, end: &str| -> Result<usize, String> {
+            rest.find(end).map(|i| i + end.len()).ok_or_else(|| format!("missing '{}'", end))
+        };
+        if rest.starts_with("<!--") {
+            rest = &rest[skip_to(rest, "-->")?..];
+        } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata.find("]]>").ok_or("unterminated CDATA section")?;
+            stack.last_mut().ok_or("unbalanced tags")?.text.push_str(&cdata[..end]);
+            rest = &cdata[end + 3..];
+        } else if rest.starts_with("<?") {
+            rest = &rest[skip_to(rest, "?>")?..];
+        } else if rest.starts_with("<!") {
+            rest = &rest[skip_to(rest, ">")?..];
+        } else if let Some(closing) = rest.strip_prefix("</") {
+            let end = closing.find('>').ok_or("unterminated end tag")?;
+            let name = local_name(closing[..end].trim());
+            let element = stack.pop().filter(|_| !stack.is_empty()).ok_or_else(|| format!("unexpected </{}>", name))?;
+            if element.name != name {
+                return Err(format!("expected </{}>, found </{}>", element.name, name));
+            }
+            stack.last_mut().ok_or("unbalanced tags")?.children.push(element);
+            rest = &closing[end + 1..];
+        } else {
+            let end = skip_to(rest, ">")?;
+            let tag = &rest[1..end - 1];
+            let (tag, self_closing) = match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            };
+            let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+            let element = Element {
+                name: local_name(&tag[..name_end]).to_string(),
+                attributes: parse_attributes(&tag[name_end..])?,
+                ..Element::default()
+            };
+            if self_closing {
+                stack.last_mut().ok_or("unbalanced tags")?.children.push(element);
+            } else {
+                stack.push(element);
+            }
+            rest = &rest[end..];
+        }
+    }
+
+    if stack.len() != 1 {
+        return Err(format!("unclosed <{}>", stack.last().map_or("", |element| element.name.as_str())));
+    }
+    stack.pop().and_then(|document| document.children.into_iter().next()).ok_or_else(|| "no root element".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_elements_attributes_and_text() {
+        let root = parse("\u{feff}<?xml version=\"1.0\"?>\n<!DOCTYPE gpx>\n<!-- exported -->\
+                          <gpx version='1.1' creator=\"A &amp; B\"><trk><name>Run</name></trk><trk/></gpx>").unwrap();
+        assert_eq!(root.name, "gpx");
+        assert_eq!(root.attribute("version"), Some("1.1"));
+        assert_eq!(root.attribute("creator"), Some("A & B"));
+        assert_eq!(root.children("trk").count(), 2);
+        assert_eq!(root.child("trk").and_then(|trk| trk.child_text("name")), Some("Run"));
+    }
+
+    #[test]
+    fn decodes_entities_and_cdata() {
+        let root = parse("<name>&lt;Fish &amp; Chips&gt; &quot;&apos;&#233;&#xE9;<![CDATA[ <raw> & ]]></name>").unwrap();
+        assert_eq!(root.text, "<Fish & Chips> \"'éé <raw> & ");
+        assert_eq!(decode_entities("&nbsp;"), Err("unknown entity '&nbsp;'".to_string()));
+        assert_eq!(decode_entities("a & b"), Err("unterminated entity".to_string()));
+    }
+
+    #[test]
+    fn drops_namespace_prefixes() {
+        let root = parse("<gpx:gpx xmlns:gpx=\"x\"><gpxtpx:TrackPointExtension><gpxtpx:hr>150</gpxtpx:hr>\
+                          </gpxtpx:TrackPointExtension></gpx:gpx>").unwrap();
+        assert_eq!(root.name, "gpx");
+        assert_eq!(root.attribute("gpx"), Some("x"));
+        let mut found = Vec::new();
+        root.descendants("hr", &mut found);
+        assert_eq!(found.iter().map(|hr| hr.text.as_str()).collect::<Vec<_>>(), ["150"]);
+    }
+
+    #[test]
+    fn refuses_malformed_documents() {
+        assert_eq!(parse("<a><b></a>").unwrap_err(), "expected </b>, found </a>");
+        assert_eq!(parse("<a><b></b>").unwrap_err(), "unclosed <a>");
+        assert_eq!(parse("<a></a></b>").unwrap_err(), "unexpected </b>");
+        assert_eq!(parse("<a x=1></a>").unwrap_err(), "unquoted attribute 'x'");
+        assert_eq!(parse("<a x=\"1></a>").unwrap_err(), "unterminated attribute 'x'");
+        assert_eq!(parse("<a><b").unwrap_err(), "missing '>'");
+        assert_eq!(parse("<!-- only a comment -->").unwrap_err(), "no root element");
+    }
+}
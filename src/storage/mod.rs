@@ -0,0 +1,158 @@
+// Persistence for users, foods, exercises and each user's profile, daily log, workouts,
+// undo history and unit preferences.
+// `TextStorage` is the original layout of text files under data/, `SqliteStorage` keeps
+// everything in a single SQLite database. Which one is used is chosen at startup.
+
+mod sqlite;
+mod text;
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::{
+    DailyLog, ExerciseDatabase, FoodDatabase, FoodDatabaseError, Journal, ProfileHistory, UnitPreferences, Username,
+    WorkoutLog,
+};
+
+pub(crate) use sqlite::SqliteStorage;
+pub(crate) use text::{TextStorage, write_log, write_workouts};
+
+pub(crate) trait Storage {
+    // (username, password hash) pairs exactly as stored, including names that no longer
+    // pass validation so that they survive a save
+    fn load_users(&self) -> io::Result<Vec<(String, String)>>;
+    fn save_users(&self, users: &[(String, String)]) -> io::Result<()>;
+
+    // Fails with FoodDatabaseError::Io when there is no food data at all. Other errors
+    // leave the foods loaded but report a problem with composites.
+    fn load_foods(&self, database: &mut FoodDatabase) -> Result<(), FoodDatabaseError>;
+    fn save_foods(&self, database: &FoodDatabase) -> io::Result<()>;
+    fn has_foods(&self) -> bool;
+    // None if no exercises have been saved yet
+    fn load_exercises(&self) -> io::Result<Option<ExerciseDatabase>>;
+    fn save_exercises(&self, database: &ExerciseDatabase) -> io::Result<()>;
+
+    // Empty if the user has no profile yet; an error if one is stored but unreadable
+    fn load_profile(&self, user: &Username) -> io::Result<ProfileHistory>;
+    fn save_profile(&self, user: &Username, history: &ProfileHistory) -> io::Result<()>;
+    // Both the food and the exercise entries
+    fn load_log(&self, user: &Username) -> io::Result<DailyLog>;
+    fn save_log(&self, user: &Username, log: &DailyLog) -> io::Result<()>;
+    fn load_workouts(&self, user: &Username) -> io::Result<WorkoutLog>;
+    fn save_workouts(&self, user: &Username, log: &WorkoutLog) -> io::Result<()>;
+    fn load_journal(&self, user: &Username) -> io::Result<Journal>;
+    fn save_journal(&self, user: &Username, journal: &Journal) -> io::Result<()>;
+    // None if the user hasn't chosen any
+    fn load_preferences(&self, user: &Username) -> io::Result<Option<UnitPreferences>>;
+    fn save_preferences(&self, user: &Username, preferences: &UnitPreferences) -> io::Result<()>;
+
+    // Warnings about the data read since the last call, e.g. that a file was upgraded to
+    // the current format or restored from its backup. The caller shows them to the user.
+    fn take_warnings(&self) -> Vec<String>;
+
+    // Names that have per-user data stored, whether or not they are registered users
+    fn user_data_names(&self) -> io::Result<Vec<String>>;
+    fn has_user_data(&self, user: &Username) -> bool;
+    fn create_user_data(&self, user: &Username) -> io::Result<()>;
+    fn rename_user_data(&self, old_name: &Username, new_name: &Username) -> io::Result<()>;
+    fn delete_user_data(&self, user: &Username) -> io::Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum StorageKind {
+    Text,
+    Sqlite,
+}
+
+impl StorageKind {
+    pub(crate) fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "text" | "txt" => Some(StorageKind::Text),
+            "sqlite" | "sqlite3" | "db" => Some(StorageKind::Sqlite),
+            _ => None,
+        }
+    }
+
+    // Opens the backend of this kind inside `data_dir`, creating it if needed
+    pub(crate) fn open(self, data_dir: &Path) -> io::Result<Rc<dyn Storage>> {
+        Ok(match self {
+            StorageKind::Text => Rc::new(TextStorage::new(data_dir)?),
+            StorageKind::Sqlite => Rc::new(SqliteStorage::open(&data_dir.join("yada.db"))?),
+        })
+    }
+}
+
+impl fmt::Display for StorageKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageKind::Text => write!(f, "text"),
+            StorageKind::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+// What `migrate` copied
+#[derive(Debug, Default)]
+pub(crate) struct MigrationSummary {
+    pub(crate) users: usize,
+    pub(crate) foods: usize,
+    pub(crate) exercises: usize,
+    pub(crate) log_entries: usize,
+    pub(crate) exercise_entries: usize,
+    pub(crate) workout_sets: usize,
+    pub(crate) skipped_users: Vec<String>, // names that aren't valid usernames, copied without data
+}
+
+// Copies all users, foods and per-user data from `from` to `to`, replacing what `to` had
+// for those users. Problems with composite foods don't stop the migration; the foods are
+// copied as they are and the problem is returned alongside the summary.
+pub(crate) fn migrate(from: &dyn Storage, to: &dyn Storage) -> Result<(MigrationSummary, Option<FoodDatabaseError>), FoodDatabaseError> {
+    let mut summary = MigrationSummary::default();
+
+    let mut foods = FoodDatabase::new();
+    let food_problem = match from.load_foods(&mut foods) {
+        Ok(()) => None,
+        Err(FoodDatabaseError::Io(e)) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(FoodDatabaseError::Io(e)) => return Err(FoodDatabaseError::Io(e)),
+        Err(e) => Some(e),
+    };
+    to.save_foods(&foods)?;
+    summary.foods = foods.foods.len();
+
+    if let Some(exercises) = from.load_exercises()? {
+        to.save_exercises(&exercises)?;
+        summary.exercises = exercises.exercises.len();
+    }
+
+    let users = from.load_users()?;
+    to.save_users(&users)?;
+    summary.users = users.len();
+
+    for (name, _) in &users {
+        let Ok(user) = Username::parse(name) else {
+            summary.skipped_users.push(name.clone());
+            continue;
+        };
+
+        to.create_user_data(&user)?;
+        let history = from.load_profile(&user)?;
+        if !history.is_empty() {
+            to.save_profile(&user, &history)?;
+        }
+        let log = from.load_log(&user)?;
+        summary.log_entries += log.entries.values().map(|entries| entries.len()).sum::<usize>();
+        summary.exercise_entries += log.exercises.values().map(|entries| entries.len()).sum::<usize>();
+        to.save_log(&user, &log)?;
+        let workouts = from.load_workouts(&user)?;
+        summary.workout_sets += workouts.sets.values().map(|sets| sets.len()).sum::<usize>();
+        to.save_workouts(&user, &workouts)?;
+        to.save_journal(&user, &from.load_journal(&user)?)?;
+        if let Some(preferences) = from.load_preferences(&user)? {
+            to.save_preferences(&user, &preferences)?;
+        }
+    }
+
+    Ok((summary, food_problem))
+}
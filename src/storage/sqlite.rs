@@ -0,0 +1,755 @@
+// Everything in one SQLite database (data/yada.db). Each save replaces the stored rows for
+// that user (or all foods / users) inside a transaction, mirroring how the text files
+// are rewritten.
+
+use std::io;
+use std::path::Path;
+
+use chrono::NaiveTime;
+use rusqlite::types::Type;
+use rusqlite::{Connection, params};
+
+use super::Storage;
+use crate::{
+    ActivityDetails, ActivityLevel, BodyMeasurements, CommandType, DailyLog, EnergyUnit, Exercise, ExerciseDatabase, ExerciseEntry,
+    Food, FoodDatabase, FoodDatabaseError, FoodEntry, Gender, Journal, Macros, Meal, NutritionSnapshot, ProfileHistory,
+    ServingSize, Unit, UnitPreferences, UnitSystem, UserProfile, Username, WeightGoal, WorkoutLog, WorkoutSet,
+};
+use crate::workout::MuscleGroup;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS users (
+        username TEXT PRIMARY KEY,
+        password TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS foods (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        keywords TEXT NOT NULL,
+        calories INTEGER NOT NULL,
+        protein REAL NOT NULL,
+        carbs REAL NOT NULL,
+        fat REAL NOT NULL,
+        fiber REAL NOT NULL,
+        sugar REAL NOT NULL,
+        is_composite INTEGER NOT NULL,
+        serving_amount REAL,
+        serving_unit TEXT,
+        archived INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS food_components (
+        food_id TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        component_id TEXT NOT NULL,
+        servings REAL NOT NULL,
+        PRIMARY KEY (food_id, position)
+    );
+    CREATE TABLE IF NOT EXISTS user_data (
+        username TEXT PRIMARY KEY,
+        units TEXT,
+        energy_unit TEXT
+    );
+    CREATE TABLE IF NOT EXISTS profile_history (
+        username TEXT NOT NULL,
+        date TEXT NOT NULL,
+        gender TEXT NOT NULL,
+        height REAL NOT NULL,
+        age INTEGER NOT NULL,
+        weight REAL NOT NULL,
+        activity_level TEXT NOT NULL,
+        calculation_method TEXT NOT NULL,
+        goal_weight REAL,
+        goal_rate REAL,
+        goal_min_calories INTEGER,
+        body_fat REAL,
+        waist REAL,
+        hip REAL,
+        neck REAL,
+        PRIMARY KEY (username, date)
+    );
+    CREATE TABLE IF NOT EXISTS log_entries (
+        username TEXT NOT NULL,
+        date TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        food_id TEXT NOT NULL,
+        servings REAL NOT NULL,
+        timestamp INTEGER NOT NULL,
+        calories_per_serving INTEGER,
+        protein REAL,
+        carbs REAL,
+        fat REAL,
+        fiber REAL,
+        sugar REAL,
+        meal TEXT NOT NULL,
+        time TEXT NOT NULL,
+        PRIMARY KEY (username, date, position)
+    );
+    CREATE TABLE IF NOT EXISTS exercises (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        keywords TEXT NOT NULL,
+        met REAL NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS exercise_entries (
+        username TEXT NOT NULL,
+        date TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        exercise_id TEXT NOT NULL,
+        minutes REAL NOT NULL,
+        timestamp INTEGER NOT NULL,
+        met REAL NOT NULL,
+        time TEXT NOT NULL,
+        start INTEGER,
+        distance REAL,
+        ascent REAL,
+        heart_rate INTEGER,
+        calories INTEGER,
+        PRIMARY KEY (username, date, position)
+    );
+    CREATE TABLE IF NOT EXISTS workout_sets (
+        username TEXT NOT NULL,
+        date TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        lift TEXT NOT NULL,
+        muscles TEXT NOT NULL,
+        reps INTEGER NOT NULL,
+        weight REAL NOT NULL,
+        rpe REAL,
+        timestamp INTEGER NOT NULL,
+        time TEXT NOT NULL,
+        PRIMARY KEY (username, date, position)
+    );
+    CREATE TABLE IF NOT EXISTS journal (
+        username TEXT NOT NULL,
+        stack TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        command TEXT NOT NULL,
+        PRIMARY KEY (username, stack, position)
+    );
+";
+
+// Stored in `PRAGMA user_version`. Databases created before versioning report 0 and
+// have the version 1 schema.
+const SCHEMA_VERSION: i32 = 8;
+
+// SQL that upgrades the schema from the version its index stands for to the next:
+// MIGRATIONS[0] upgrades version 1 to 2, and so on
+const MIGRATIONS: [&str; 7] = [
+    // Profiles become dated records; the existing profile applies from the start, like
+    // ProfileHistory::UNDATED
+    "CREATE TABLE profile_history (
+         username TEXT NOT NULL,
+         date TEXT NOT NULL,
+         gender TEXT NOT NULL,
+         height REAL NOT NULL,
+         age INTEGER NOT NULL,
+         weight REAL NOT NULL,
+         activity_level TEXT NOT NULL,
+         calculation_method TEXT NOT NULL,
+         PRIMARY KEY (username, date)
+     );
+     INSERT INTO profile_history
+         SELECT username, '1970-01-01', gender, height, age, weight, activity_level, calculation_method
+         FROM profiles;
+     DROP TABLE profiles;",
+    // Profiles gain an optional weight goal, stored in three columns that are all NULL
+    // without one
+    "ALTER TABLE profile_history ADD COLUMN goal_weight REAL;
+     ALTER TABLE profile_history ADD COLUMN goal_rate REAL;
+     ALTER TABLE profile_history ADD COLUMN goal_min_calories INTEGER;",
+    // Optional body composition figures
+    "ALTER TABLE profile_history ADD COLUMN body_fat REAL;
+     ALTER TABLE profile_history ADD COLUMN waist REAL;
+     ALTER TABLE profile_history ADD COLUMN hip REAL;
+     ALTER TABLE profile_history ADD COLUMN neck REAL;",
+    // Unit preferences; NULL means the default
+    "ALTER TABLE user_data ADD COLUMN units TEXT;
+     ALTER TABLE user_data ADD COLUMN energy_unit TEXT;",
+    // The exercise tables are new. SCHEMA creates the exercises table; exercise_entries
+    // is created as it was in version 6, so the migrations after it can alter it.
+    "CREATE TABLE exercise_entries (
+         username TEXT NOT NULL,
+         date TEXT NOT NULL,
+         position INTEGER NOT NULL,
+         exercise_id TEXT NOT NULL,
+         minutes REAL NOT NULL,
+         timestamp INTEGER NOT NULL,
+         met REAL NOT NULL,
+         time TEXT NOT NULL,
+         PRIMARY KEY (username, date, position)
+     );",
+    // The workout_sets table is new, so SCHEMA creates it
+    "",
+    // Details of activities imported from devices, all NULL for exercise logged by hand
+    "ALTER TABLE exercise_entries ADD COLUMN start INTEGER;
+     ALTER TABLE exercise_entries ADD COLUMN distance REAL;
+     ALTER TABLE exercise_entries ADD COLUMN ascent REAL;
+     ALTER TABLE exercise_entries ADD COLUMN heart_rate INTEGER;
+     ALTER TABLE exercise_entries ADD COLUMN calories INTEGER;",
+];
+
+// Per-user tables, in the order they are renamed and cleared
+const USER_TABLES: [&str; 6] = [
+    "profile_history", "log_entries", "exercise_entries", "workout_sets", "journal", "user_data",
+];
+
+pub(crate) struct SqliteStorage {
+    conn: Connection,
+}
+
+fn db_error(e: rusqlite::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+// A stored text value in `column` that doesn't parse
+fn invalid_data(column: usize, message: String) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(column, Type::Text, message.into())
+}
+
+impl SqliteStorage {
+    pub(crate) fn open(path: &Path) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let conn = Connection::open(path).map_err(db_error)?;
+        let storage = SqliteStorage { conn };
+        storage.upgrade_schema()?;
+        Ok(storage)
+    }
+
+    // Creates or upgrades the tables. A database from a newer version is refused with
+    // ErrorKind::Unsupported before anything is changed.
+    fn upgrade_schema(&self) -> io::Result<()> {
+        let version: i32 = self.conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(db_error)?;
+        if version > SCHEMA_VERSION {
+            return Err(io::Error::new(io::ErrorKind::Unsupported,
+                                      format!("written by a newer version of YADA (schema {}, this version reads up to {})",
+                                              version, SCHEMA_VERSION)));
+        }
+
+        // A new database gets the current schema, an existing one is migrated
+        let tx = self.conn.unchecked_transaction().map_err(db_error)?;
+        let has_tables: bool = tx.query_row("SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table')",
+                                            [], |row| row.get(0)).map_err(db_error)?;
+        if has_tables {
+            for migration in MIGRATIONS.iter().skip(version.max(1) as usize - 1) {
+                tx.execute_batch(migration).map_err(db_error)?;
+            }
+        }
+        tx.execute_batch(SCHEMA).map_err(db_error)?;
+        tx.pragma_update(None, "user_version", SCHEMA_VERSION).map_err(db_error)?;
+        tx.commit().map_err(db_error)
+    }
+
+    fn load_food_rows(&self) -> rusqlite::Result<Vec<Food>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, keywords, calories, protein, carbs, fat, fiber, sugar,
+                    is_composite, serving_amount, serving_unit, archived
+             FROM foods ORDER BY id")?;
+        let mut foods = stmt.query_map([], |row| {
+            let keywords: String = row.get(2)?;
+            let serving_amount: Option<f64> = row.get(10)?;
+            let serving_unit: Option<String> = row.get(11)?;
+            let serving = match (serving_amount, serving_unit) {
+                (Some(amount), Some(unit)) => {
+                    let unit = Unit::from_str(&unit)
+                        .ok_or_else(|| invalid_data(11, format!("unknown unit '{}'", unit)))?;
+                    Some(ServingSize { amount, unit })
+                },
+                _ => None,
+            };
+
+            Ok(Food {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                keywords: keywords.split('|').filter(|k| !k.is_empty()).map(|k| k.to_string()).collect(),
+                calories_per_serving: row.get(3)?,
+                macros: Macros::new(row.get(4)?, row.get(5)?, row.get(6)?, row.get(7)?, row.get(8)?),
+                is_composite: row.get(9)?,
+                components: Vec::new(),
+                serving,
+                archived: row.get(12)?,
+            })
+        })?.collect::<rusqlite::Result<Vec<Food>>>()?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT component_id, servings FROM food_components WHERE food_id = ?1 ORDER BY position")?;
+        for food in foods.iter_mut().filter(|food| food.is_composite) {
+            food.components = stmt.query_map([&food.id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<_>>()?;
+        }
+
+        Ok(foods)
+    }
+
+    fn load_profile_rows(&self, user: &Username) -> rusqlite::Result<ProfileHistory> {
+        let mut stmt = self.conn.prepare(
+            "SELECT date, gender, height, age, weight, activity_level, calculation_method,
+                    goal_weight, goal_rate, goal_min_calories, body_fat, waist, hip, neck
+             FROM profile_history WHERE username = ?1 ORDER BY date")?;
+        let rows = stmt.query_map([user.as_str()], |row| {
+            let gender: String = row.get(1)?;
+            let activity_level: String = row.get(5)?;
+            let mut profile = UserProfile::new(
+                user.to_string(),
+                Gender::from_str(&gender).unwrap_or(Gender::Other),
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                ActivityLevel::from_str(&activity_level)
+                    .ok_or_else(|| invalid_data(5, format!("unknown activity level '{}'", activity_level)))?,
+            );
+            profile.set_calculation_method(&row.get::<_, String>(6)?);
+            let goal: (Option<f64>, Option<f64>) = (row.get(7)?, row.get(8)?);
+            if let (Some(target_weight), Some(weekly_rate)) = goal {
+                profile.goal = Some(WeightGoal { target_weight, weekly_rate, min_calories: row.get(9)? });
+            }
+            profile.body = BodyMeasurements { body_fat: row.get(10)?, waist: row.get(11)?, hip: row.get(12)?, neck: row.get(13)? };
+            Ok((row.get::<_, String>(0)?, profile))
+        })?;
+
+        let mut history = ProfileHistory::new();
+        for row in rows {
+            let (date, profile) = row?;
+            history.set(&date, profile);
+        }
+        Ok(history)
+    }
+
+    fn load_log_rows(&self, user: &Username) -> rusqlite::Result<DailyLog> {
+        let mut stmt = self.conn.prepare(
+            "SELECT date, food_id, servings, timestamp, calories_per_serving,
+                    protein, carbs, fat, fiber, sugar, meal, time
+             FROM log_entries WHERE username = ?1 ORDER BY date, position")?;
+        let rows = stmt.query_map([user.as_str()], |row| {
+            let calories: Option<u32> = row.get(4)?;
+            let snapshot = match calories {
+                Some(calories_per_serving) => Some(NutritionSnapshot {
+                    calories_per_serving,
+                    macros: Macros::new(
+                        row.get::<_, Option<f64>>(5)?.unwrap_or(0.0),
+                        row.get::<_, Option<f64>>(6)?.unwrap_or(0.0),
+                        row.get::<_, Option<f64>>(7)?.unwrap_or(0.0),
+                        row.get::<_, Option<f64>>(8)?.unwrap_or(0.0),
+                        row.get::<_, Option<f64>>(9)?.unwrap_or(0.0),
+                    ),
+                }),
+                None => None,
+            };
+
+            let meal: String = row.get(10)?;
+            let time: String = row.get(11)?;
+            let entry = FoodEntry {
+                food_id: row.get(1)?,
+                servings: row.get(2)?,
+                timestamp: row.get::<_, i64>(3)? as u64,
+                snapshot,
+                meal: Meal::from_str(&meal).ok_or_else(|| invalid_data(10, format!("invalid meal '{}'", meal)))?,
+                time: NaiveTime::parse_from_str(&time, "%H:%M")
+                    .map_err(|_| invalid_data(11, format!("invalid time '{}'", time)))?,
+            };
+            Ok((row.get::<_, String>(0)?, entry))
+        })?;
+
+        let mut log = DailyLog::new();
+        for row in rows {
+            let (date, entry) = row?;
+            log.entries.entry(date).or_default().push(entry);
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT date, exercise_id, minutes, timestamp, met, time, start, distance, ascent, heart_rate, calories
+             FROM exercise_entries WHERE username = ?1 ORDER BY date, position")?;
+        let rows = stmt.query_map([user.as_str()], |row| {
+            let time: String = row.get(5)?;
+            let details = match row.get::<_, Option<i64>>(6)? {
+                Some(start) => Some(ActivityDetails {
+                    start: start as u64,
+                    distance: row.get(7)?,
+                    ascent: row.get(8)?,
+                    heart_rate: row.get(9)?,
+                    calories: row.get(10)?,
+                }),
+                None => None,
+            };
+            let entry = ExerciseEntry {
+                exercise_id: row.get(1)?,
+                minutes: row.get(2)?,
+                timestamp: row.get::<_, i64>(3)? as u64,
+                met: row.get(4)?,
+                time: NaiveTime::parse_from_str(&time, "%H:%M")
+                    .map_err(|_| invalid_data(5, format!("invalid time '{}'", time)))?,
+                details,
+            };
+            Ok((row.get::<_, String>(0)?, entry))
+        })?;
+        for row in rows {
+            let (date, entry) = row?;
+            log.exercises.entry(date).or_default().push(entry);
+        }
+        Ok(log)
+    }
+
+    fn load_workout_rows(&self, user: &Username) -> rusqlite::Result<WorkoutLog> {
+        let mut stmt = self.conn.prepare(
+            "SELECT date, lift, muscles, reps, weight, rpe, timestamp, time
+             FROM workout_sets WHERE username = ?1 ORDER BY date, position")?;
+        let rows = stmt.query_map([user.as_str()], |row| {
+            let muscles: String = row.get(2)?;
+            let time: String = row.get(7)?;
+            let set = WorkoutSet {
+                lift: row.get(1)?,
+                muscles: MuscleGroup::parse_list(&muscles)
+                    .ok_or_else(|| invalid_data(2, format!("invalid muscle groups '{}'", muscles)))?,
+                reps: row.get(3)?,
+                weight: row.get(4)?,
+                rpe: row.get(5)?,
+                timestamp: row.get::<_, i64>(6)? as u64,
+                time: NaiveTime::parse_from_str(&time, "%H:%M")
+                    .map_err(|_| invalid_data(7, format!("invalid time '{}'", time)))?,
+            };
+            Ok((row.get::<_, String>(0)?, set))
+        })?;
+
+        let mut log = WorkoutLog::new();
+        for row in rows {
+            let (date, set) = row?;
+            log.sets.entry(date).or_default().push(set);
+        }
+        Ok(log)
+    }
+
+    // None while the table is empty
+    fn load_exercise_rows(&self) -> rusqlite::Result<Option<ExerciseDatabase>> {
+        let mut stmt = self.conn.prepare("SELECT id, name, keywords, met FROM exercises ORDER BY id")?;
+        let exercises = stmt.query_map([], |row| {
+            let keywords: String = row.get(2)?;
+            Ok(Exercise {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                keywords: keywords.split('|').filter(|k| !k.is_empty()).map(|k| k.to_string()).collect(),
+                met: row.get(3)?,
+            })
+        })?.collect::<rusqlite::Result<Vec<Exercise>>>()?;
+
+        if exercises.is_empty() {
+            return Ok(None);
+        }
+        let mut database = ExerciseDatabase::new();
+        for exercise in exercises {
+            database.add_exercise(exercise);
+        }
+        Ok(Some(database))
+    }
+
+    fn load_journal_rows(&self, user: &Username) -> rusqlite::Result<Journal> {
+        let mut stmt = self.conn.prepare(
+            "SELECT stack, command FROM journal WHERE username = ?1 ORDER BY stack, position")?;
+        let rows = stmt.query_map([user.as_str()], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+
+        let mut journal = Journal::new();
+        for row in rows {
+            let (stack, command) = row?;
+            let command = CommandType::from_string(&command)
+                .ok_or_else(|| invalid_data(1, format!("invalid journal command '{}'", command)))?;
+            match stack.as_str() {
+                "undo" => journal.undo_stack.push(command),
+                "redo" => journal.redo_stack.push(command),
+                _ => {},
+            }
+        }
+        Ok(journal)
+    }
+
+    // None until the user has saved preferences, which sets both columns
+    fn load_preference_row(&self, user: &Username) -> rusqlite::Result<Option<UnitPreferences>> {
+        let mut stmt = self.conn.prepare("SELECT units, energy_unit FROM user_data WHERE username = ?1")?;
+        let mut rows = stmt.query([user.as_str()])?;
+        let Some(row) = rows.next()? else {
+            return Ok(None);
+        };
+
+        let (units, energy) = (row.get::<_, Option<String>>(0)?, row.get::<_, Option<String>>(1)?);
+        if units.is_none() && energy.is_none() {
+            return Ok(None);
+        }
+        let mut preferences = UnitPreferences::default();
+        if let Some(units) = units {
+            preferences.system = UnitSystem::from_str(&units)
+                .ok_or_else(|| invalid_data(0, format!("unknown units '{}'", units)))?;
+        }
+        if let Some(energy) = energy {
+            preferences.energy = EnergyUnit::from_str(&energy)
+                .ok_or_else(|| invalid_data(1, format!("unknown energy unit '{}'", energy)))?;
+        }
+        Ok(Some(preferences))
+    }
+
+    // Marks the user as having data, so user_data_names lists them
+    fn touch_user(&self, user: &Username) -> rusqlite::Result<()> {
+        self.conn.execute("INSERT OR IGNORE INTO user_data (username) VALUES (?1)", [user.as_str()])?;
+        Ok(())
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn load_users(&self) -> io::Result<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare("SELECT username, password FROM users ORDER BY username")
+            .map_err(db_error)?;
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .and_then(|rows| rows.collect())
+            .map_err(db_error)
+    }
+
+    fn save_users(&self, users: &[(String, String)]) -> io::Result<()> {
+        let tx = self.conn.unchecked_transaction().map_err(db_error)?;
+        tx.execute("DELETE FROM users", []).map_err(db_error)?;
+        for (username, password) in users {
+            tx.execute("INSERT OR REPLACE INTO users (username, password) VALUES (?1, ?2)",
+                       params![username, password]).map_err(db_error)?;
+        }
+        tx.commit().map_err(db_error)
+    }
+
+    fn load_foods(&self, database: &mut FoodDatabase) -> Result<(), FoodDatabaseError> {
+        if !self.has_foods() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "the database has no foods").into());
+        }
+        for food in self.load_food_rows().map_err(db_error)? {
+            database.add_food(food);
+        }
+        database.calculate_composite_calories()
+    }
+
+    fn save_foods(&self, database: &FoodDatabase) -> io::Result<()> {
+        let tx = self.conn.unchecked_transaction().map_err(db_error)?;
+        tx.execute("DELETE FROM foods", []).map_err(db_error)?;
+        tx.execute("DELETE FROM food_components", []).map_err(db_error)?;
+
+        for food in database.foods.values() {
+            let macros = &food.macros;
+            tx.execute(
+                "INSERT INTO foods (id, name, keywords, calories, protein, carbs, fat, fiber, sugar,
+                                    is_composite, serving_amount, serving_unit, archived)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                params![
+                    food.id, food.name, food.keywords.join("|"), food.calories_per_serving,
+                    macros.protein, macros.carbs, macros.fat, macros.fiber, macros.sugar,
+                    food.is_composite, food.serving.map(|s| s.amount), food.serving.map(|s| s.unit.symbol()),
+                    food.archived,
+                ],
+            ).map_err(db_error)?;
+
+            for (position, (component_id, servings)) in food.components.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO food_components (food_id, position, component_id, servings) VALUES (?1, ?2, ?3, ?4)",
+                    params![food.id, position as i64, component_id, servings],
+                ).map_err(db_error)?;
+            }
+        }
+
+        tx.commit().map_err(db_error)
+    }
+
+    fn has_foods(&self) -> bool {
+        self.conn.query_row("SELECT EXISTS (SELECT 1 FROM foods)", [], |row| row.get(0))
+            .unwrap_or(false)
+    }
+
+    fn load_exercises(&self) -> io::Result<Option<ExerciseDatabase>> {
+        self.load_exercise_rows().map_err(db_error)
+    }
+
+    fn save_exercises(&self, database: &ExerciseDatabase) -> io::Result<()> {
+        let tx = self.conn.unchecked_transaction().map_err(db_error)?;
+        tx.execute("DELETE FROM exercises", []).map_err(db_error)?;
+        for exercise in database.exercises.values() {
+            tx.execute("INSERT INTO exercises (id, name, keywords, met) VALUES (?1, ?2, ?3, ?4)",
+                       params![exercise.id, exercise.name, exercise.keywords.join("|"), exercise.met])
+                .map_err(db_error)?;
+        }
+        tx.commit().map_err(db_error)
+    }
+
+    fn load_profile(&self, user: &Username) -> io::Result<ProfileHistory> {
+        self.load_profile_rows(user).map_err(db_error)
+    }
+
+    fn save_profile(&self, user: &Username, history: &ProfileHistory) -> io::Result<()> {
+        let tx = self.conn.unchecked_transaction().map_err(db_error)?;
+        self.touch_user(user).map_err(db_error)?;
+        tx.execute("DELETE FROM profile_history WHERE username = ?1", [user.as_str()]).map_err(db_error)?;
+
+        for (date, profile) in &history.records {
+            tx.execute(
+                "INSERT INTO profile_history (username, date, gender, height, age, weight, activity_level, calculation_method,
+                                              goal_weight, goal_rate, goal_min_calories, body_fat, waist, hip, neck)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+                params![
+                    user.as_str(), date, format!("{:?}", profile.gender), profile.height, profile.age,
+                    profile.weight, format!("{:?}", profile.activity_level), profile.calculation_method,
+                    profile.goal.map(|goal| goal.target_weight), profile.goal.map(|goal| goal.weekly_rate),
+                    profile.goal.and_then(|goal| goal.min_calories), profile.body.body_fat, profile.body.waist,
+                    profile.body.hip, profile.body.neck,
+                ],
+            ).map_err(db_error)?;
+        }
+
+        tx.commit().map_err(db_error)
+    }
+
+    fn load_log(&self, user: &Username) -> io::Result<DailyLog> {
+        self.load_log_rows(user).map_err(db_error)
+    }
+
+    fn save_log(&self, user: &Username, log: &DailyLog) -> io::Result<()> {
+        let tx = self.conn.unchecked_transaction().map_err(db_error)?;
+        self.touch_user(user).map_err(db_error)?;
+        tx.execute("DELETE FROM log_entries WHERE username = ?1", [user.as_str()]).map_err(db_error)?;
+
+        for (date, entries) in &log.entries {
+            for (position, entry) in entries.iter().enumerate() {
+                let snapshot = entry.snapshot.as_ref();
+                let macros = snapshot.map(|s| s.macros);
+                tx.execute(
+                    "INSERT INTO log_entries (username, date, position, food_id, servings, timestamp,
+                                              calories_per_serving, protein, carbs, fat, fiber, sugar, meal, time)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                    params![
+                        user.as_str(), date, position as i64, entry.food_id, entry.servings, entry.timestamp as i64,
+                        snapshot.map(|s| s.calories_per_serving),
+                        macros.map(|m| m.protein), macros.map(|m| m.carbs), macros.map(|m| m.fat),
+                        macros.map(|m| m.fiber), macros.map(|m| m.sugar),
+                        entry.meal.to_string(), entry.time.format("%H:%M").to_string(),
+                    ],
+                ).map_err(db_error)?;
+            }
+        }
+
+        tx.execute("DELETE FROM exercise_entries WHERE username = ?1", [user.as_str()]).map_err(db_error)?;
+        for (date, entries) in &log.exercises {
+            for (position, entry) in entries.iter().enumerate() {
+                let details = entry.details.as_ref();
+                tx.execute(
+                    "INSERT INTO exercise_entries (username, date, position, exercise_id, minutes, timestamp, met, time,
+                                                   start, distance, ascent, heart_rate, calories)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                    params![
+                        user.as_str(), date, position as i64, entry.exercise_id, entry.minutes, entry.timestamp as i64,
+                        entry.met, entry.time.format("%H:%M").to_string(),
+                        details.map(|d| d.start as i64), details.and_then(|d| d.distance), details.and_then(|d| d.ascent),
+                        details.and_then(|d| d.heart_rate), details.and_then(|d| d.calories),
+                    ],
+                ).map_err(db_error)?;
+            }
+        }
+
+        tx.commit().map_err(db_error)
+    }
+
+    fn load_workouts(&self, user: &Username) -> io::Result<WorkoutLog> {
+        self.load_workout_rows(user).map_err(db_error)
+    }
+
+    fn save_workouts(&self, user: &Username, log: &WorkoutLog) -> io::Result<()> {
+        let tx = self.conn.unchecked_transaction().map_err(db_error)?;
+        self.touch_user(user).map_err(db_error)?;
+        tx.execute("DELETE FROM workout_sets WHERE username = ?1", [user.as_str()]).map_err(db_error)?;
+
+        for (date, sets) in &log.sets {
+            for (position, set) in sets.iter().enumerate() {
+                let muscles: Vec<String> = set.muscles.iter().map(|muscle| muscle.to_string()).collect();
+                tx.execute(
+                    "INSERT INTO workout_sets (username, date, position, lift, muscles, reps, weight, rpe, timestamp, time)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    params![
+                        user.as_str(), date, position as i64, set.lift, muscles.join("|"), set.reps, set.weight,
+                        set.rpe, set.timestamp as i64, set.time.format("%H:%M").to_string(),
+                    ],
+                ).map_err(db_error)?;
+            }
+        }
+
+        tx.commit().map_err(db_error)
+    }
+
+    fn load_journal(&self, user: &Username) -> io::Result<Journal> {
+        self.load_journal_rows(user).map_err(db_error)
+    }
+
+    fn save_journal(&self, user: &Username, journal: &Journal) -> io::Result<()> {
+        let tx = self.conn.unchecked_transaction().map_err(db_error)?;
+        self.touch_user(user).map_err(db_error)?;
+        tx.execute("DELETE FROM journal WHERE username = ?1", [user.as_str()]).map_err(db_error)?;
+
+        for (stack, commands) in [("undo", &journal.undo_stack), ("redo", &journal.redo_stack)] {
+            for (position, command) in commands.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO journal (username, stack, position, command) VALUES (?1, ?2, ?3, ?4)",
+                    params![user.as_str(), stack, position as i64, command.to_string()],
+                ).map_err(db_error)?;
+            }
+        }
+
+        tx.commit().map_err(db_error)
+    }
+
+    fn load_preferences(&self, user: &Username) -> io::Result<Option<UnitPreferences>> {
+        self.load_preference_row(user).map_err(db_error)
+    }
+
+    fn save_preferences(&self, user: &Username, preferences: &UnitPreferences) -> io::Result<()> {
+        self.touch_user(user).map_err(db_error)?;
+        self.conn.execute("UPDATE user_data SET units = ?1, energy_unit = ?2 WHERE username = ?3",
+                          params![preferences.system.to_string(), preferences.energy.to_string(), user.as_str()])
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    // The database has no file formats to upgrade or backups to restore
+    fn take_warnings(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn user_data_names(&self) -> io::Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT username FROM user_data ORDER BY username")
+            .map_err(db_error)?;
+        stmt.query_map([], |row| row.get(0))
+            .and_then(|rows| rows.collect())
+            .map_err(db_error)
+    }
+
+    fn has_user_data(&self, user: &Username) -> bool {
+        // Compare ignoring case, like a case-insensitive file system would for the text layout
+        self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM user_data WHERE username = ?1 COLLATE NOCASE)",
+            [user.as_str()],
+            |row| row.get(0),
+        ).unwrap_or(false)
+    }
+
+    fn create_user_data(&self, user: &Username) -> io::Result<()> {
+        self.touch_user(user).map_err(db_error)
+    }
+
+    fn rename_user_data(&self, old_name: &Username, new_name: &Username) -> io::Result<()> {
+        let tx = self.conn.unchecked_transaction().map_err(db_error)?;
+        for table in USER_TABLES {
+            tx.execute(&format!("UPDATE {} SET username = ?1 WHERE username = ?2", table),
+                       [new_name.as_str(), old_name.as_str()]).map_err(db_error)?;
+        }
+        tx.execute("INSERT OR IGNORE INTO user_data (username) VALUES (?1)", [new_name.as_str()])
+            .map_err(db_error)?;
+        tx.commit().map_err(db_error)
+    }
+
+    fn delete_user_data(&self, user: &Username) -> io::Result<()> {
+        let tx = self.conn.unchecked_transaction().map_err(db_error)?;
+        for table in USER_TABLES {
+            tx.execute(&format!("DELETE FROM {} WHERE username = ?1", table), [user.as_str()])
+                .map_err(db_error)?;
+        }
+        tx.commit().map_err(db_error)
+    }
+}
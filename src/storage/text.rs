@@ -0,0 +1,240 @@
+// The original storage layout:
+//   data/users.txt            username,password-hash
+//   data/foods.txt            one food per line
+//   data/exercises.txt        one exercise per line
+//   data/<user>/profile.txt   date,profile per line
+//   data/<user>/log.txt       date,entry per line
+//   data/<user>/exercise.txt  date,exercise entry per line
+//   data/<user>/workouts.txt  date,set per line
+//   data/<user>/journal.txt   undo/redo history
+//   data/<user>/preferences.txt  unit preferences
+
+use std::cell::RefCell;
+use std::fs::create_dir_all;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::Storage;
+use crate::datafile;
+use crate::{
+    DailyLog, ExerciseDatabase, FoodDatabase, FoodDatabaseError, Journal, ProfileHistory, UnitPreferences, Username,
+    WorkoutLog,
+};
+
+const USERS_FORMAT: datafile::Format = datafile::Format {
+    name: "users",
+    version: 1,
+    migrations: &[],
+};
+
+pub(crate) struct TextStorage {
+    data_dir: PathBuf,
+    warnings: RefCell<Vec<String>>, // from the files read since the last take_warnings
+}
+
+impl TextStorage {
+    pub(crate) fn new(data_dir: &Path) -> io::Result<Self> {
+        create_dir_all(data_dir)?;
+        Ok(TextStorage { data_dir: data_dir.to_path_buf(), warnings: RefCell::new(Vec::new()) })
+    }
+
+    // Safe to join onto the data directory: a Username has no separators or dots
+    fn user_dir(&self, user: &Username) -> PathBuf {
+        self.data_dir.join(user.as_str())
+    }
+
+    fn user_file(&self, user: &Username, name: &str) -> io::Result<PathBuf> {
+        let dir = self.user_dir(user);
+        create_dir_all(&dir)?;
+        Ok(dir.join(name))
+    }
+}
+
+// Writes log.txt and exercise.txt into a user's directory, or an account export
+pub(crate) fn write_log(dir: &Path, log: &DailyLog) -> io::Result<()> {
+    log.save_to_file(&dir.join("log.txt"))?;
+    // Users who have never logged exercise don't get an empty file
+    let path = dir.join("exercise.txt");
+    if path.exists() || !log.exercises.is_empty() {
+        log.save_exercises_to_file(&path)?;
+    }
+    Ok(())
+}
+
+// Writes workouts.txt into a user's directory, or an account export
+pub(crate) fn write_workouts(dir: &Path, log: &WorkoutLog) -> io::Result<()> {
+    // Like exercise.txt, only written once there is something in it
+    let path = dir.join("workouts.txt");
+    if path.exists() || !log.is_empty() {
+        log.save_to_file(&path)?;
+    }
+    Ok(())
+}
+
+impl Storage for TextStorage {
+    fn load_users(&self) -> io::Result<Vec<(String, String)>> {
+        let path = self.data_dir.join("users.txt");
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        // Password hashes contain commas, so only split off the username
+        let (users, warnings) = datafile::read_lines(&path, &USERS_FORMAT, |line| {
+            line.split_once(',')
+                .map(|(username, password)| (username.to_string(), password.to_string()))
+                .ok_or_else(|| "missing password".to_string())
+        })?;
+        self.warnings.borrow_mut().extend(warnings);
+        Ok(users)
+    }
+
+    fn save_users(&self, users: &[(String, String)]) -> io::Result<()> {
+        let mut content = String::new();
+        for (username, password) in users {
+            content.push_str(&format!("{},{}\n", username, password));
+        }
+        datafile::write_lines(&self.data_dir.join("users.txt"), &USERS_FORMAT, &content)
+    }
+
+    fn load_foods(&self, database: &mut FoodDatabase) -> Result<(), FoodDatabaseError> {
+        database.load_from_file(&self.data_dir.join("foods.txt"), &mut self.warnings.borrow_mut())
+    }
+
+    fn save_foods(&self, database: &FoodDatabase) -> io::Result<()> {
+        database.save_to_file(&self.data_dir.join("foods.txt"))
+    }
+
+    fn has_foods(&self) -> bool {
+        self.data_dir.join("foods.txt").exists()
+    }
+
+    fn load_exercises(&self) -> io::Result<Option<ExerciseDatabase>> {
+        let path = self.data_dir.join("exercises.txt");
+        if !path.exists() {
+            return Ok(None);
+        }
+        let mut database = ExerciseDatabase::new();
+        database.load_from_file(&path, &mut self.warnings.borrow_mut())?;
+        Ok(Some(database))
+    }
+
+    fn save_exercises(&self, database: &ExerciseDatabase) -> io::Result<()> {
+        database.save_to_file(&self.data_dir.join("exercises.txt"))
+    }
+
+    fn load_profile(&self, user: &Username) -> io::Result<ProfileHistory> {
+        let path = self.user_dir(user).join("profile.txt");
+        if !path.exists() {
+            return Ok(ProfileHistory::new());
+        }
+        let history = ProfileHistory::load_from_file(&path, &mut self.warnings.borrow_mut())?;
+        if history.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "profile file is empty or corrupted"));
+        }
+        Ok(history)
+    }
+
+    fn save_profile(&self, user: &Username, history: &ProfileHistory) -> io::Result<()> {
+        history.save_to_file(&self.user_file(user, "profile.txt")?)
+    }
+
+    fn load_log(&self, user: &Username) -> io::Result<DailyLog> {
+        let mut log = DailyLog::new();
+        let path = self.user_dir(user).join("log.txt");
+        if path.exists() {
+            log.load_from_file(&path, &mut self.warnings.borrow_mut())?;
+        }
+        let path = self.user_dir(user).join("exercise.txt");
+        if path.exists() {
+            log.load_exercises_from_file(&path, &mut self.warnings.borrow_mut())?;
+        }
+        Ok(log)
+    }
+
+    fn save_log(&self, user: &Username, log: &DailyLog) -> io::Result<()> {
+        let dir = self.user_dir(user);
+        create_dir_all(&dir)?;
+        write_log(&dir, log)
+    }
+
+    fn load_workouts(&self, user: &Username) -> io::Result<WorkoutLog> {
+        let mut log = WorkoutLog::new();
+        let path = self.user_dir(user).join("workouts.txt");
+        if path.exists() {
+            log.load_from_file(&path, &mut self.warnings.borrow_mut())?;
+        }
+        Ok(log)
+    }
+
+    fn save_workouts(&self, user: &Username, log: &WorkoutLog) -> io::Result<()> {
+        let dir = self.user_dir(user);
+        create_dir_all(&dir)?;
+        write_workouts(&dir, log)
+    }
+
+    fn load_journal(&self, user: &Username) -> io::Result<Journal> {
+        let mut journal = Journal::new();
+        let path = self.user_dir(user).join("journal.txt");
+        if path.exists() {
+            journal.load_from_file(&path, &mut self.warnings.borrow_mut())?;
+        }
+        Ok(journal)
+    }
+
+    fn save_journal(&self, user: &Username, journal: &Journal) -> io::Result<()> {
+        journal.save_to_file(&self.user_file(user, "journal.txt")?)
+    }
+
+    fn load_preferences(&self, user: &Username) -> io::Result<Option<UnitPreferences>> {
+        let path = self.user_dir(user).join("preferences.txt");
+        if !path.exists() {
+            return Ok(None);
+        }
+        UnitPreferences::load_from_file(&path, &mut self.warnings.borrow_mut()).map(Some)
+    }
+
+    fn save_preferences(&self, user: &Username, preferences: &UnitPreferences) -> io::Result<()> {
+        preferences.save_to_file(&self.user_file(user, "preferences.txt")?)
+    }
+
+    fn take_warnings(&self) -> Vec<String> {
+        self.warnings.take()
+    }
+
+    fn user_data_names(&self) -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(&self.data_dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn has_user_data(&self, user: &Username) -> bool {
+        self.user_dir(user).exists()
+    }
+
+    fn create_user_data(&self, user: &Username) -> io::Result<()> {
+        create_dir_all(self.user_dir(user))
+    }
+
+    fn rename_user_data(&self, old_name: &Username, new_name: &Username) -> io::Result<()> {
+        let old_dir = self.user_dir(old_name);
+        if old_dir.exists() {
+            std::fs::rename(old_dir, self.user_dir(new_name))
+        } else {
+            create_dir_all(self.user_dir(new_name))
+        }
+    }
+
+    fn delete_user_data(&self, user: &Username) -> io::Result<()> {
+        let dir = self.user_dir(user);
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+}
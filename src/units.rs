@@ -0,0 +1,391 @@
+// Per-user unit preferences. Everything is stored in metric units (kg, cm) and kcal; these
+// only change how amounts are shown and how typed amounts are read. Typed amounts may name
+// their unit whatever the preference, e.g. `180 lb`, `5'10"`, `5 ft 10 in` or `2000 kJ`.
+// A bare number is in the preferred unit.
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use crate::{csv, datafile, format_amount};
+
+const KG_PER_LB: f64 = 0.45359237;
+const KG_PER_STONE: f64 = 6.35029318;
+const CM_PER_INCH: f64 = 2.54;
+const KJ_PER_KCAL: f64 = 4.184;
+const M_PER_MILE: f64 = 1609.344;
+const M_PER_FOOT: f64 = 0.3048;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) enum UnitSystem {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+impl UnitSystem {
+    pub(crate) fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "metric" | "si" => Some(UnitSystem::Metric),
+            "imperial" | "us" => Some(UnitSystem::Imperial),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for UnitSystem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnitSystem::Metric => write!(f, "metric"),
+            UnitSystem::Imperial => write!(f, "imperial"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) enum EnergyUnit {
+    #[default]
+    Kcal,
+    Kj,
+}
+
+impl EnergyUnit {
+    pub(crate) fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "kcal" | "cal" | "calories" => Some(EnergyUnit::Kcal),
+            "kj" | "kilojoules" => Some(EnergyUnit::Kj),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for EnergyUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EnergyUnit::Kcal => write!(f, "kcal"),
+            EnergyUnit::Kj => write!(f, "kJ"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct UnitPreferences {
+    pub(crate) system: UnitSystem,
+    pub(crate) energy: EnergyUnit,
+}
+
+// Splits "5 ft 10 in", "5'10\"" or "180lb" into (number, lowercase unit) pairs. A number
+// without a unit gets an empty one.
+fn split_amounts(input: &str) -> Option<Vec<(f64, String)>> {
+    let mut amounts = Vec::new();
+    let mut rest = input.trim();
+
+    while !rest.is_empty() {
+        let number_end = rest.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(rest.len());
+        let number = rest[..number_end].parse::<f64>().ok()?;
+        rest = rest[number_end..].trim_start();
+
+        let unit_end = match rest.chars().next() {
+            Some('\'' | '"' | '′' | '″') => rest.chars().next().map_or(0, |c| c.len_utf8()),
+            _ => rest.find(|c: char| !c.is_alphabetic()).unwrap_or(rest.len()),
+        };
+        amounts.push((number, rest[..unit_end].to_lowercase()));
+        rest = rest[unit_end..].trim_start();
+    }
+
+    if amounts.is_empty() { None } else { Some(amounts) }
+}
+
+// Sums the amounts in `input` using `factor` to turn each unit into the stored one. A bare
+// number on its own is in `default_unit`; after another amount it is in the next smaller
+// unit `factor` knows (e.g. the 10 in 5'10), given by `smaller_unit`.
+fn parse_sum(input: &str, default_unit: &str, smaller_unit: impl Fn(&str) -> Option<&'static str>,
+             factor: impl Fn(&str) -> Option<f64>) -> Option<f64> {
+    let amounts = split_amounts(input)?;
+    let mut total = 0.0;
+    let mut previous: Option<&str> = None;
+
+    for (number, unit) in &amounts {
+        let unit = match (unit.as_str(), previous) {
+            ("", None) => default_unit,
+            ("", Some(previous)) => smaller_unit(previous)?,
+            (unit, _) => unit,
+        };
+        total += number * factor(unit)?;
+        previous = Some(unit);
+    }
+
+    Some(total).filter(|total| total.is_finite())
+}
+
+fn kg_per(unit: &str) -> Option<f64> {
+    match unit {
+        "kg" | "kgs" | "kilogram" | "kilograms" => Some(1.0),
+        "g" | "gram" | "grams" => Some(0.001),
+        "lb" | "lbs" | "pound" | "pounds" => Some(KG_PER_LB),
+        "st" | "stone" | "stones" => Some(KG_PER_STONE),
+        "oz" | "ounce" | "ounces" => Some(KG_PER_LB / 16.0),
+        _ => None,
+    }
+}
+
+fn cm_per(unit: &str) -> Option<f64> {
+    match unit {
+        "cm" | "centimeter" | "centimeters" | "centimetre" | "centimetres" => Some(1.0),
+        "m" | "meter" | "meters" | "metre" | "metres" => Some(100.0),
+        "mm" => Some(0.1),
+        "in" | "inch" | "inches" | "\"" | "″" => Some(CM_PER_INCH),
+        "ft" | "foot" | "feet" | "'" | "′" => Some(12.0 * CM_PER_INCH),
+        _ => None,
+    }
+}
+
+fn kcal_per(unit: &str) -> Option<f64> {
+    match unit {
+        "kcal" | "cal" | "calorie" | "calories" => Some(1.0),
+        "kj" | "kilojoule" | "kilojoules" => Some(1.0 / KJ_PER_KCAL),
+        _ => None,
+    }
+}
+
+// The unit a bare number after `unit` is in
+fn smaller_unit(unit: &str) -> Option<&'static str> {
+    match unit {
+        "st" | "stone" | "stones" => Some("lb"),
+        "lb" | "lbs" | "pound" | "pounds" => Some("oz"),
+        "m" | "meter" | "meters" | "metre" | "metres" => Some("cm"),
+        "ft" | "foot" | "feet" | "'" | "′" => Some("in"),
+        _ => None,
+    }
+}
+
+impl UnitPreferences {
+    pub(crate) fn weight_unit(&self) -> &'static str {
+        match self.system {
+            UnitSystem::Metric => "kg",
+            UnitSystem::Imperial => "lb",
+        }
+    }
+
+    // Unit of body circumferences such as the waist
+    pub(crate) fn length_unit(&self) -> &'static str {
+        match self.system {
+            UnitSystem::Metric => "cm",
+            UnitSystem::Imperial => "in",
+        }
+    }
+
+    // How to type a height, for prompts
+    pub(crate) fn height_hint(&self) -> &'static str {
+        match self.system {
+            UnitSystem::Metric => "cm",
+            UnitSystem::Imperial => "e.g. 5'10\"",
+        }
+    }
+
+    // A weight in kg in the preferred unit, without the unit
+    pub(crate) fn weight_value(&self, kg: f64) -> f64 {
+        match self.system {
+            UnitSystem::Metric => kg,
+            UnitSystem::Imperial => kg / KG_PER_LB,
+        }
+    }
+
+    // e.g. "80 kg" or "176.37 lb"
+    pub(crate) fn weight(&self, kg: f64) -> String {
+        format!("{} {}", format_amount(self.weight_value(kg)), self.weight_unit())
+    }
+
+    // e.g. "180 cm" or "5'10.9\""
+    pub(crate) fn height(&self, cm: f64) -> String {
+        match self.system {
+            UnitSystem::Metric => format!("{} cm", format_amount(cm)),
+            UnitSystem::Imperial => {
+                let inches = (cm / CM_PER_INCH * 10.0).round() / 10.0;
+                let feet = (inches / 12.0).floor();
+                format!("{}'{}\"", feet, format_amount(inches - feet * 12.0))
+            },
+        }
+    }
+
+    // A body circumference, e.g. "90 cm" or "35.43 in"
+    pub(crate) fn length(&self, cm: f64) -> String {
+        match self.system {
+            UnitSystem::Metric => format!("{} cm", format_amount(cm)),
+            UnitSystem::Imperial => format!("{} in", format_amount(cm / CM_PER_INCH)),
+        }
+    }
+
+    // A distance covered, e.g. "5.02 km" or "3.12 mi"
+    pub(crate) fn distance(&self, m: f64) -> String {
+        match self.system {
+            UnitSystem::Metric => format!("{} km", format_amount(m / 1000.0)),
+            UnitSystem::Imperial => format!("{} mi", format_amount(m / M_PER_MILE)),
+        }
+    }
+
+    // An elevation gain, e.g. "120 m" or "394 ft", rounded to whole units
+    pub(crate) fn elevation(&self, m: f64) -> String {
+        match self.system {
+            UnitSystem::Metric => format!("{:.0} m", m),
+            UnitSystem::Imperial => format!("{:.0} ft", m / M_PER_FOOT),
+        }
+    }
+
+    // An amount of kcal in the preferred energy unit, without the unit
+    pub(crate) fn energy_value(&self, kcal: f64) -> f64 {
+        match self.energy {
+            EnergyUnit::Kcal => kcal,
+            EnergyUnit::Kj => kcal * KJ_PER_KCAL,
+        }
+    }
+
+    // e.g. "2000 kcal" or "8368 kJ", rounded to whole units
+    pub(crate) fn energy(&self, kcal: f64) -> String {
+        format!("{:.0} {}", self.energy_value(kcal), self.energy)
+    }
+
+    // A typed weight in kg
+    pub(crate) fn parse_weight(&self, input: &str) -> Option<f64> {
+        parse_sum(input, self.weight_unit(), smaller_unit, kg_per)
+    }
+
+    // A typed height or body circumference in cm. A bare imperial number is in inches.
+    pub(crate) fn parse_length(&self, input: &str) -> Option<f64> {
+        parse_sum(input, self.length_unit(), smaller_unit, cm_per)
+    }
+
+    // A typed amount of energy in kcal
+    pub(crate) fn parse_energy(&self, input: &str) -> Option<f64> {
+        let default_unit = match self.energy {
+            EnergyUnit::Kcal => "kcal",
+            EnergyUnit::Kj => "kj",
+        };
+        parse_sum(input, default_unit, |_| None, kcal_per)
+    }
+}
+
+// The preferences file: one `setting,value` line each for `units` and `energy`
+const PREFERENCES_FORMAT: datafile::Format = datafile::Format {
+    name: "preferences",
+    version: 1,
+    migrations: &[],
+};
+
+impl UnitPreferences {
+    pub(crate) fn load_from_file(path: &Path, warnings: &mut Vec<String>) -> io::Result<Self> {
+        let mut preferences = UnitPreferences::default();
+        let (settings, file_warnings) = datafile::read_lines(path, &PREFERENCES_FORMAT, |line| {
+            let fields = csv::split(line.trim(), ',')?;
+            match fields.as_slice() {
+                [setting, value] => Ok((setting.clone(), value.clone())),
+                _ => Err(format!("expected 2 fields, got {}", fields.len())),
+            }
+        })?;
+        warnings.extend(file_warnings);
+
+        for (setting, value) in settings {
+            let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("invalid {} '{}'", setting, value));
+            match setting.as_str() {
+                "units" => preferences.system = UnitSystem::from_str(&value).ok_or_else(invalid)?,
+                "energy" => preferences.energy = EnergyUnit::from_str(&value).ok_or_else(invalid)?,
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown setting '{}'", setting))),
+            }
+        }
+        Ok(preferences)
+    }
+
+    pub(crate) fn save_to_file(&self, path: &Path) -> io::Result<()> {
+        let content = format!("units,{}\nenergy,{}\n", self.system, self.energy);
+        datafile::write_lines(path, &PREFERENCES_FORMAT, &content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METRIC: UnitPreferences = UnitPreferences { system: UnitSystem::Metric, energy: EnergyUnit::Kcal };
+    const IMPERIAL: UnitPreferences = UnitPreferences { system: UnitSystem::Imperial, energy: EnergyUnit::Kj };
+
+    fn assert_near(actual: Option<f64>, expected: f64) {
+        match actual {
+            Some(actual) => assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected),
+            None => panic!("expected {}, got nothing", expected),
+        }
+    }
+
+    #[test]
+    fn amounts_are_split_into_numbers_and_units() {
+        assert_eq!(split_amounts("5'10\""), Some(vec![(5.0, "'".to_string()), (10.0, "\"".to_string())]));
+        assert_eq!(split_amounts(" 180LB 8 oz"), Some(vec![(180.0, "lb".to_string()), (8.0, "oz".to_string())]));
+        assert_eq!(split_amounts("11 st 4"), Some(vec![(11.0, "st".to_string()), (4.0, String::new())]));
+        assert_eq!(split_amounts(""), None);
+        assert_eq!(split_amounts("ft"), None);
+    }
+
+    #[test]
+    fn a_bare_number_after_a_unit_is_in_the_next_smaller_one() {
+        assert_eq!(smaller_unit("st"), Some("lb"));
+        assert_eq!(smaller_unit("pounds"), Some("oz"));
+        assert_eq!(smaller_unit("'"), Some("in"));
+        assert_eq!(smaller_unit("m"), Some("cm"));
+        assert_eq!(smaller_unit("in"), None);
+        assert_eq!(smaller_unit("kg"), None);
+    }
+
+    #[test]
+    fn compound_heights() {
+        let five_ten = 70.0 * CM_PER_INCH;
+        for input in ["5'10\"", "5'10", "5′10″", "5 ft 10 in", "5ft10in", "5 feet 10", "70 in"] {
+            assert_near(METRIC.parse_length(input), five_ten);
+        }
+        assert_near(METRIC.parse_length("1 m 78"), 178.0);
+        // A height in whole feet
+        assert_near(METRIC.parse_length("5'"), 60.0 * CM_PER_INCH);
+    }
+
+    #[test]
+    fn compound_weights() {
+        assert_near(METRIC.parse_weight("11 st 4"), 11.0 * KG_PER_STONE + 4.0 * KG_PER_LB);
+        assert_near(METRIC.parse_weight("11 stone 4 lb"), 11.0 * KG_PER_STONE + 4.0 * KG_PER_LB);
+        assert_near(METRIC.parse_weight("180 lb 8 oz"), 180.5 * KG_PER_LB);
+        assert_near(METRIC.parse_weight("75 kg 500 g"), 75.5);
+    }
+
+    #[test]
+    fn a_bare_number_is_in_the_preferred_unit() {
+        assert_near(METRIC.parse_weight("80"), 80.0);
+        assert_near(IMPERIAL.parse_weight("176"), 176.0 * KG_PER_LB);
+        assert_near(METRIC.parse_length("180"), 180.0);
+        assert_near(IMPERIAL.parse_length("70"), 70.0 * CM_PER_INCH);
+        assert_near(METRIC.parse_energy("2000"), 2000.0);
+        assert_near(IMPERIAL.parse_energy("8368"), 2000.0);
+        // Whatever the preference, a unit can be given
+        assert_near(METRIC.parse_weight("176 lb"), 176.0 * KG_PER_LB);
+        assert_near(IMPERIAL.parse_energy("2000 kcal"), 2000.0);
+    }
+
+    #[test]
+    fn bad_amounts_are_refused() {
+        for input in ["", "-80", "80 kg -2", "5'-2", "'10", "1.2.3 kg", "5 ft 10 in 3", "80 kg kg"] {
+            assert_eq!(METRIC.parse_weight(input).or(METRIC.parse_length(input)), None, "{:?}", input);
+        }
+        // Unknown units, and units of the wrong kind
+        assert_eq!(METRIC.parse_weight("12 furlongs"), None);
+        assert_eq!(METRIC.parse_weight("180 cm"), None);
+        assert_eq!(METRIC.parse_length("80 kg"), None);
+        assert_eq!(METRIC.parse_energy("5 ft"), None);
+        // No smaller unit to fall back to
+        assert_eq!(METRIC.parse_weight("80 kg 5"), None);
+        assert_eq!(METRIC.parse_energy("2000 kcal 5"), None);
+    }
+
+    #[test]
+    fn amounts_are_shown_in_the_preferred_units() {
+        assert_eq!(IMPERIAL.height(70.0 * CM_PER_INCH), "5'10\"");
+        assert_eq!(METRIC.height(178.0), "178 cm");
+        assert_eq!(IMPERIAL.energy(2000.0), "8368 kJ");
+        assert_eq!(METRIC.energy(2000.4), "2000 kcal");
+    }
+}
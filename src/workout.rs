@@ -0,0 +1,401 @@
+// Strength training: the sets each user lifts, with their personal records, estimated
+// one-rep maxes and weekly volume per muscle group. Weights are stored in kg like
+// everything else; a weight of 0 is a bodyweight set.
+//
+// Each set keeps the name and muscle groups of its lift, so lifts the user makes up are
+// remembered by the log itself and the same lift may be typed in any case.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime};
+
+use crate::clock::Clock;
+use crate::units::UnitPreferences;
+use crate::{CommandType, csv, datafile, insert_dated, remove_dated};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) enum MuscleGroup {
+    Chest,
+    Back,
+    Shoulders,
+    Biceps,
+    Triceps,
+    Quads,
+    Hamstrings,
+    Glutes,
+    Calves,
+    Core,
+}
+
+impl MuscleGroup {
+    pub(crate) const ALL: [MuscleGroup; 10] = [
+        MuscleGroup::Chest, MuscleGroup::Back, MuscleGroup::Shoulders, MuscleGroup::Biceps, MuscleGroup::Triceps,
+        MuscleGroup::Quads, MuscleGroup::Hamstrings, MuscleGroup::Glutes, MuscleGroup::Calves, MuscleGroup::Core,
+    ];
+
+    pub(crate) fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "chest" | "pecs" => Some(MuscleGroup::Chest),
+            "back" | "lats" => Some(MuscleGroup::Back),
+            "shoulders" | "delts" => Some(MuscleGroup::Shoulders),
+            "biceps" => Some(MuscleGroup::Biceps),
+            "triceps" => Some(MuscleGroup::Triceps),
+            "quads" | "quadriceps" => Some(MuscleGroup::Quads),
+            "hamstrings" => Some(MuscleGroup::Hamstrings),
+            "glutes" => Some(MuscleGroup::Glutes),
+            "calves" => Some(MuscleGroup::Calves),
+            "core" | "abs" => Some(MuscleGroup::Core),
+            _ => None,
+        }
+    }
+
+    // A list such as "chest triceps" or "chest,triceps". None if any name is unknown or
+    // there are none.
+    pub(crate) fn parse_list(s: &str) -> Option<Vec<Self>> {
+        let mut muscles = Vec::new();
+        for name in s.split([' ', ',', '|']).filter(|name| !name.is_empty()) {
+            let muscle = MuscleGroup::from_str(name)?;
+            if !muscles.contains(&muscle) {
+                muscles.push(muscle);
+            }
+        }
+        if muscles.is_empty() { None } else { Some(muscles) }
+    }
+}
+
+impl fmt::Display for MuscleGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            MuscleGroup::Chest => "chest",
+            MuscleGroup::Back => "back",
+            MuscleGroup::Shoulders => "shoulders",
+            MuscleGroup::Biceps => "biceps",
+            MuscleGroup::Triceps => "triceps",
+            MuscleGroup::Quads => "quads",
+            MuscleGroup::Hamstrings => "hamstrings",
+            MuscleGroup::Glutes => "glutes",
+            MuscleGroup::Calves => "calves",
+            MuscleGroup::Core => "core",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Lifts offered before the user has logged any of their own, with the muscle groups
+// whose weekly volume they count towards
+const COMMON_LIFTS: [(&str, &[MuscleGroup]); 16] = [
+    ("Bench Press", &[MuscleGroup::Chest, MuscleGroup::Triceps]),
+    ("Incline Bench Press", &[MuscleGroup::Chest, MuscleGroup::Shoulders]),
+    ("Overhead Press", &[MuscleGroup::Shoulders, MuscleGroup::Triceps]),
+    ("Squat", &[MuscleGroup::Quads, MuscleGroup::Glutes]),
+    ("Front Squat", &[MuscleGroup::Quads]),
+    ("Deadlift", &[MuscleGroup::Back, MuscleGroup::Hamstrings, MuscleGroup::Glutes]),
+    ("Romanian Deadlift", &[MuscleGroup::Hamstrings, MuscleGroup::Glutes]),
+    ("Barbell Row", &[MuscleGroup::Back, MuscleGroup::Biceps]),
+    ("Pull-up", &[MuscleGroup::Back, MuscleGroup::Biceps]),
+    ("Lat Pulldown", &[MuscleGroup::Back, MuscleGroup::Biceps]),
+    ("Dip", &[MuscleGroup::Chest, MuscleGroup::Triceps]),
+    ("Biceps Curl", &[MuscleGroup::Biceps]),
+    ("Triceps Extension", &[MuscleGroup::Triceps]),
+    ("Lunge", &[MuscleGroup::Quads, MuscleGroup::Glutes]),
+    ("Calf Raise", &[MuscleGroup::Calves]),
+    ("Hanging Leg Raise", &[MuscleGroup::Core]),
+];
+
+// Rating of perceived exertion: 10 is a set taken to failure, 8 leaves two reps in reserve
+pub(crate) fn parse_rpe(s: &str) -> Option<f64> {
+    s.trim().parse::<f64>().ok().filter(|rpe| (1.0..=10.0).contains(rpe))
+}
+
+// A set's weight, or "bodyweight" for a weight of 0
+pub(crate) fn describe_load(units: &UnitPreferences, kg: f64) -> String {
+    if kg > 0.0 { units.weight(kg) } else { "bodyweight".to_string() }
+}
+
+// One set of a lift
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct WorkoutSet {
+    pub(crate) lift: String,
+    pub(crate) muscles: Vec<MuscleGroup>,
+    pub(crate) reps: u32,
+    pub(crate) weight: f64,
+    pub(crate) rpe: Option<f64>,
+    pub(crate) timestamp: u64,
+    pub(crate) time: NaiveTime, // local time of day the set was done
+}
+
+impl WorkoutSet {
+    pub(crate) fn new(lift: &str, muscles: Vec<MuscleGroup>, reps: u32, weight: f64, rpe: Option<f64>,
+                      time: NaiveTime, clock: &dyn Clock) -> Self {
+        WorkoutSet {
+            lift: lift.to_string(),
+            muscles,
+            reps,
+            weight,
+            rpe,
+            timestamp: clock.now().timestamp().max(0) as u64,
+            time,
+        }
+    }
+
+    // Weight moved: reps × kg
+    pub(crate) fn volume(&self) -> f64 {
+        self.reps as f64 * self.weight
+    }
+
+    // The Epley estimate weight × (1 + reps / 30), counting the reps left in reserve when
+    // the RPE is known, so an easy set of 5 estimates more than a set of 5 to failure
+    pub(crate) fn estimated_1rm(&self) -> f64 {
+        let reserve = self.rpe.map_or(0.0, |rpe| 10.0 - rpe);
+        let reps = self.reps as f64 + reserve;
+        if reps <= 1.0 {
+            self.weight
+        } else {
+            self.weight * (1.0 + reps / 30.0)
+        }
+    }
+
+    // Whether this set beats `other` by weight, or by reps at the same weight
+    fn is_heavier_than(&self, other: &WorkoutSet) -> bool {
+        (self.weight, self.reps) > (other.weight, other.reps)
+    }
+
+    // lift,muscles,reps,weight,rpe,timestamp,HH:MM with the muscles separated by '|' and an
+    // empty rpe if none was given
+    pub(crate) fn from_fields(parts: &[String]) -> Result<Self, String> {
+        if parts.len() != 7 {
+            return Err(format!("expected 7 set fields, got {}", parts.len()));
+        }
+
+        let rpe = match parts[4].as_str() {
+            "" => None,
+            rpe => Some(parse_rpe(rpe).ok_or_else(|| format!("invalid RPE '{}'", rpe))?),
+        };
+        Ok(WorkoutSet {
+            lift: parts[0].clone(),
+            muscles: MuscleGroup::parse_list(&parts[1]).ok_or_else(|| format!("invalid muscle groups '{}'", parts[1]))?,
+            reps: parts[2].parse::<u32>().map_err(|_| format!("invalid reps '{}'", parts[2]))?,
+            weight: parts[3].parse::<f64>().map_err(|_| format!("invalid weight '{}'", parts[3]))?,
+            rpe,
+            timestamp: parts[5].parse::<u64>().map_err(|_| format!("invalid timestamp '{}'", parts[5]))?,
+            time: NaiveTime::parse_from_str(&parts[6], "%H:%M").map_err(|_| format!("invalid time '{}'", parts[6]))?,
+        })
+    }
+
+    pub(crate) fn from_string(s: &str) -> Result<Self, String> {
+        Self::from_fields(&csv::split(s, ',')?)
+    }
+
+    pub(crate) fn to_fields(&self) -> Vec<String> {
+        let muscles: Vec<String> = self.muscles.iter().map(|muscle| muscle.to_string()).collect();
+        vec![
+            self.lift.clone(),
+            muscles.join("|"),
+            self.reps.to_string(),
+            self.weight.to_string(),
+            self.rpe.map_or(String::new(), |rpe| rpe.to_string()),
+            self.timestamp.to_string(),
+            self.time.format("%H:%M").to_string(),
+        ]
+    }
+}
+
+impl fmt::Display for WorkoutSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", csv::join(&self.to_fields(), ','))
+    }
+}
+
+// The best sets of one lift, each with the date it was lifted
+pub(crate) struct PersonalRecord<'a> {
+    pub(crate) lift: &'a str,
+    pub(crate) heaviest: (&'a str, &'a WorkoutSet),
+    pub(crate) best_1rm: (&'a str, &'a WorkoutSet),
+}
+
+// Sets and total volume of one muscle group in a week
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct MuscleVolume {
+    pub(crate) sets: usize,
+    pub(crate) volume: f64,
+}
+
+const WORKOUT_LOG_FORMAT: datafile::Format = datafile::Format {
+    name: "workouts",
+    version: 1,
+    migrations: &[],
+};
+
+// One user's sets by date, kept in data/<user>/workouts.txt
+pub(crate) struct WorkoutLog {
+    pub(crate) sets: HashMap<String, Vec<WorkoutSet>>, // date -> sets in the order they were logged
+}
+
+impl WorkoutLog {
+    pub(crate) fn new() -> Self {
+        WorkoutLog {
+            sets: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.sets.values().all(|sets| sets.is_empty())
+    }
+
+    // Returns the command to record in the journal
+    pub(crate) fn add_set(&mut self, date: &str, set: WorkoutSet) -> CommandType {
+        let sets = self.sets.entry(date.to_string()).or_default();
+        sets.push(set.clone());
+        CommandType::AddSet(date.to_string(), sets.len() - 1, set)
+    }
+
+    // Returns the command to record in the journal, or None if there is no such set
+    pub(crate) fn delete_set(&mut self, date: &str, index: usize) -> Option<CommandType> {
+        let sets = self.sets.get_mut(date)?;
+        if index >= sets.len() {
+            return None;
+        }
+
+        let set = sets.remove(index);
+        Some(CommandType::DeleteSet(date.to_string(), index, set))
+    }
+
+    pub(crate) fn insert_set(&mut self, date: &str, index: usize, set: WorkoutSet) {
+        insert_dated(&mut self.sets, date, index, set);
+    }
+
+    pub(crate) fn remove_set(&mut self, date: &str, index: usize, set: &WorkoutSet) -> bool {
+        remove_dated(&mut self.sets, date, index, set)
+    }
+
+    pub(crate) fn get_sets_for_date(&self, date: &str) -> Vec<&WorkoutSet> {
+        self.sets.get(date).map_or(Vec::new(), |sets| sets.iter().collect())
+    }
+
+    // Every lift in the log and the common ones, with their muscle groups, sorted by name.
+    // A lift logged under a common name keeps the muscle groups it was last logged with.
+    pub(crate) fn known_lifts(&self) -> Vec<(String, Vec<MuscleGroup>)> {
+        let mut lifts: HashMap<String, (String, Vec<MuscleGroup>)> = COMMON_LIFTS.iter()
+            .map(|(name, muscles)| (name.to_lowercase(), (name.to_string(), muscles.to_vec())))
+            .collect();
+        for (_, set) in self.dated_sets() {
+            lifts.insert(set.lift.to_lowercase(), (set.lift.clone(), set.muscles.clone()));
+        }
+
+        let mut lifts: Vec<(String, Vec<MuscleGroup>)> = lifts.into_values().collect();
+        lifts.sort_by_key(|(name, _)| name.to_lowercase());
+        lifts
+    }
+
+    // The lift with this name (ignoring case), from the log or the common lifts
+    pub(crate) fn find_lift(&self, name: &str) -> Option<(String, Vec<MuscleGroup>)> {
+        let name = name.trim().to_lowercase();
+        self.known_lifts().into_iter().find(|(lift, _)| lift.to_lowercase() == name)
+    }
+
+    // All sets with their dates, oldest first
+    fn dated_sets(&self) -> Vec<(&str, &WorkoutSet)> {
+        let mut dates: Vec<&String> = self.sets.keys().collect();
+        dates.sort();
+        dates.into_iter()
+            .flat_map(|date| self.sets[date].iter().map(move |set| (date.as_str(), set)))
+            .collect()
+    }
+
+    // The heaviest set and the best estimated 1RM of each lift, sorted by lift. The
+    // earliest set wins a tie, since that is when the record was set.
+    pub(crate) fn personal_records(&self) -> Vec<PersonalRecord<'_>> {
+        let mut records: HashMap<String, PersonalRecord> = HashMap::new();
+
+        for (date, set) in self.dated_sets() {
+            let record = records.entry(set.lift.to_lowercase()).or_insert(PersonalRecord {
+                lift: &set.lift,
+                heaviest: (date, set),
+                best_1rm: (date, set),
+            });
+            if set.is_heavier_than(record.heaviest.1) {
+                record.heaviest = (date, set);
+            }
+            if set.estimated_1rm() > record.best_1rm.1.estimated_1rm() {
+                record.best_1rm = (date, set);
+            }
+        }
+
+        let mut records: Vec<PersonalRecord> = records.into_values().collect();
+        records.sort_by_key(|record| record.lift.to_lowercase());
+        records
+    }
+
+    // What `set` would beat among the records of its lift: the heaviest set, the best
+    // estimated 1RM, or both. Empty if it beats neither, or if the lift has no sets yet.
+    pub(crate) fn records_beaten_by(&self, set: &WorkoutSet) -> Vec<&'static str> {
+        let lift = set.lift.to_lowercase();
+        let Some(record) = self.personal_records().into_iter().find(|record| record.lift.to_lowercase() == lift) else {
+            return Vec::new();
+        };
+
+        let mut beaten = Vec::new();
+        if set.is_heavier_than(record.heaviest.1) {
+            beaten.push("heaviest set");
+        }
+        if set.estimated_1rm() > record.best_1rm.1.estimated_1rm() {
+            beaten.push("estimated 1RM");
+        }
+        beaten
+    }
+
+    // Monday of the week that `date` is in
+    pub(crate) fn week_start(date: NaiveDate) -> NaiveDate {
+        date - Duration::days(date.weekday().num_days_from_monday() as i64)
+    }
+
+    // Sets and volume per muscle group in the Monday-to-Sunday week containing `date`.
+    // A set counts fully towards each of its lift's muscle groups.
+    pub(crate) fn weekly_volume(&self, date: NaiveDate) -> Vec<(MuscleGroup, MuscleVolume)> {
+        let start = WorkoutLog::week_start(date);
+        let mut volumes: HashMap<MuscleGroup, MuscleVolume> = HashMap::new();
+
+        for offset in 0..7 {
+            let day = (start + Duration::days(offset)).format("%Y-%m-%d").to_string();
+            for set in self.get_sets_for_date(&day) {
+                for muscle in &set.muscles {
+                    let volume = volumes.entry(*muscle).or_default();
+                    volume.sets += 1;
+                    volume.volume += set.volume();
+                }
+            }
+        }
+
+        MuscleGroup::ALL.iter()
+            .filter_map(|muscle| volumes.get(muscle).map(|volume| (*muscle, *volume)))
+            .collect()
+    }
+
+    // date,lift,muscles,reps,weight,rpe,timestamp,HH:MM
+    fn parse_line(line: &str) -> Result<(String, WorkoutSet), String> {
+        let fields = csv::split(line, ',')?;
+        let (date, set) = fields.split_first().ok_or("empty line")?;
+        WorkoutSet::from_fields(set).map(|set| (date.clone(), set))
+    }
+
+    pub(crate) fn load_from_file(&mut self, path: &Path, warnings: &mut Vec<String>) -> io::Result<()> {
+        let (sets, file_warnings) = datafile::read_lines(path, &WORKOUT_LOG_FORMAT, WorkoutLog::parse_line)?;
+        warnings.extend(file_warnings);
+        for (date, set) in sets {
+            self.sets.entry(date).or_default().push(set);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn save_to_file(&self, path: &Path) -> io::Result<()> {
+        let mut content = String::new();
+        for (date, set) in self.dated_sets() {
+            content.push_str(&format!("{},{}\n", csv::quote(date, ','), set));
+        }
+
+        datafile::write_lines(path, &WORKOUT_LOG_FORMAT, &content)
+    }
+}
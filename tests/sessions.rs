@@ -0,0 +1,367 @@
+// Scripted interactive sessions against a temporary data directory. Each session types
+// its lines into the menu and returns everything the application printed; data saved by
+// one session is loaded by the next, as when the program is restarted.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use chrono::{DateTime, Local, TimeZone};
+use yada::clock::Clock;
+use yada::console::Console;
+use yada::{Config, YadaApplication};
+
+const TODAY: &str = "2025-04-08";
+
+// 08:15 on TODAY, so food added without a time goes to breakfast
+struct FixedClock;
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2025, 4, 8, 8, 15, 0).unwrap()
+    }
+}
+
+struct ScriptedConsole {
+    input: Rc<RefCell<VecDeque<String>>>,
+    output: Rc<RefCell<String>>,
+}
+
+impl Console for ScriptedConsole {
+    fn read_line(&self) -> Option<String> {
+        self.input.borrow_mut().pop_front()
+    }
+
+    fn write_str(&self, text: &str) {
+        self.output.borrow_mut().push_str(text);
+    }
+}
+
+// A data directory that is removed when the test ends
+struct DataDir(PathBuf);
+
+impl DataDir {
+    fn new(test: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("yada-test-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&path);
+        DataDir(path)
+    }
+
+    // Runs one session typing `lines` and returns its output. Every line must be read.
+    fn session(&self, lines: &[&str]) -> String {
+        self.session_with(Config::new(&self.0), lines)
+    }
+
+    fn session_with(&self, config: Config, lines: &[&str]) -> String {
+        let input = Rc::new(RefCell::new(lines.iter().map(|line| line.to_string()).collect::<VecDeque<_>>()));
+        let output = Rc::new(RefCell::new(String::new()));
+        let console = ScriptedConsole { input: Rc::clone(&input), output: Rc::clone(&output) };
+
+        let mut app = YadaApplication::open(config, Box::new(console), Box::new(FixedClock))
+            .expect("could not open the data directory");
+        app.run();
+
+        let unread: Vec<String> = input.borrow().iter().cloned().collect();
+        assert!(unread.is_empty(), "the session ended before reading {:?}\n{}", unread, output.borrow());
+        output.take()
+    }
+}
+
+impl Drop for DataDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+// Registers alice and answers the profile questions
+const REGISTER: [&str; 8] = ["2", "alice", "correct horse", "F", "165", "30", "60", "2"];
+const LOGIN: [&str; 3] = ["1", "alice", "correct horse"];
+// Adds 2 eggs at the default time and meal (found by searching for "egg")
+const ADD_EGGS: [&str; 8] = ["1", "1", "egg", "y", "1", "2", "", ""];
+// Logs 30 minutes of swimming at 07:00 (the only activity found for "swim")
+const LOG_SWIM: [&str; 6] = ["17", "1", "swim", "1", "30", "07:00"];
+// Starts logging a set of bench press, chosen by name; the reps, weight, RPE and time follow
+const LOG_BENCH: [&str; 3] = ["18", "2", "bench press"];
+const VIEW_LOG: &str = "2";
+const EXIT: &str = "0";
+
+#[test]
+fn register_creates_a_profile_and_starts_today() {
+    let dir = DataDir::new("register");
+    let output = dir.session(&[&REGISTER[..], &[EXIT]].concat());
+
+    assert!(output.contains("Registration successful. Welcome, alice!"), "{}", output);
+    assert!(output.contains("Profile created successfully!"), "{}", output);
+    assert!(output.contains(&format!("Current Date: {}", TODAY)), "{}", output);
+    assert!(output.ends_with("Thank you for using YADA. Goodbye!\n"), "{}", output);
+
+    let output = dir.session(&[&LOGIN[..], &[EXIT]].concat());
+    assert!(output.contains("Login successful. Welcome, alice!"), "{}", output);
+}
+
+#[test]
+fn logged_food_is_kept_between_sessions() {
+    let dir = DataDir::new("log-food");
+    let output = dir.session(&[&REGISTER[..], &ADD_EGGS, &[EXIT]].concat());
+    assert!(output.contains("Added 2 serving(s) of Egg to Breakfast at 08:15."), "{}", output);
+
+    let output = dir.session(&[&LOGIN[..], &[VIEW_LOG, EXIT]].concat());
+    assert!(output.contains(&format!("Food Log for {}", TODAY)), "{}", output);
+    assert!(output.contains("1. 08:15 | Egg | 2 | 156 kcal"), "{}", output);
+    assert!(output.contains("Total Calories: 156 kcal"), "{}", output);
+}
+
+#[test]
+fn awkward_food_names_survive_a_restart() {
+    let dir = DataDir::new("food-name");
+    // A carriage return pasted into the name can't split its line in foods.txt
+    let add_food = ["6", "chips", "Fish\rChips, \"large\"", "fish chips", "300", "", ""];
+    dir.session(&[&REGISTER[..], &add_food, &[EXIT]].concat());
+
+    let add_chips = ["1", "1", "chips", "y", "1", "1", "", ""];
+    let output = dir.session(&[&LOGIN[..], &add_chips, &[VIEW_LOG, EXIT]].concat());
+    assert!(output.contains("1. 08:15 | Fish Chips, \"large\" | 1 | 300 kcal"), "{}", output);
+    assert!(!output.contains("damaged"), "{}", output);
+}
+
+#[test]
+fn upgraded_and_damaged_files_are_reported() {
+    let dir = DataDir::new("file-warnings");
+    dir.session(&[&REGISTER[..], &ADD_EGGS, &[EXIT]].concat());
+
+    // A log from before files had headers
+    let log = dir.0.join("alice").join("log.txt");
+    let content = fs::read_to_string(&log).unwrap();
+    fs::write(&log, content.split_once('\n').unwrap().1).unwrap();
+    let output = dir.session(&[&LOGIN[..], &[VIEW_LOG, EXIT]].concat());
+    assert!(output.contains("log.txt from format 1 to 2; the old file was kept as"), "{}", output);
+    assert!(output.contains("1. 08:15 | Egg | 2 | 156 kcal"), "{}", output);
+
+    let mut content = fs::read_to_string(&log).unwrap();
+    content.push_str("not a log entry\n");
+    fs::write(&log, content).unwrap();
+    let output = dir.session(&[&LOGIN[..], &[VIEW_LOG, EXIT]].concat());
+    assert!(output.contains("log.txt is damaged (line 3:"), "{}", output);
+    assert!(output.contains("1. 08:15 | Egg | 2 | 156 kcal"), "{}", output);
+}
+
+#[test]
+fn a_user_registering_after_a_logout_starts_afresh() {
+    let dir = DataDir::new("second-user");
+    let update_weight = ["5", "2025-01-01", "8", "1", "58"];
+    let register_bob = ["2", "bob99", "battery staple", "M", "180", "40", "80", "2"];
+    let output = dir.session(&[&REGISTER[..], &update_weight, &["11"], &register_bob, &[EXIT]].concat());
+    assert!(output.contains("Registration successful. Welcome, bob99!"), "{}", output);
+    // Back on today's date, not the one alice was looking at
+    let last_menu = &output[output.rfind("Current Date:").unwrap()..];
+    assert!(last_menu.starts_with(&format!("Current Date: {}", TODAY)), "{}", output);
+
+    let profile = fs::read_to_string(dir.0.join("bob99").join("profile.txt")).unwrap();
+    assert!(!profile.contains("alice"), "{}", profile);
+    assert!(!profile.contains("2025-01-01"), "{}", profile);
+    assert!(profile.contains(&format!("{},bob99,", TODAY)), "{}", profile);
+}
+
+#[test]
+fn undo_history_is_not_passed_on_to_the_next_user() {
+    let dir = DataDir::new("second-user-undo");
+    let register_bob = ["2", "bob99", "battery staple", "M", "180", "40", "80", "2"];
+    let output = dir.session(&[&REGISTER[..], &ADD_EGGS, &["11"], &register_bob, &["4", "1", EXIT]].concat());
+    assert!(output.contains("Nothing to undo."), "{}", output);
+    assert!(!output.contains("Could not undo"), "{}", output);
+
+    let journal = fs::read_to_string(dir.0.join("bob99").join("journal.txt")).unwrap();
+    assert!(!journal.contains("egg"), "{}", journal);
+    let output = dir.session(&[&LOGIN[..], &[VIEW_LOG, EXIT]].concat());
+    assert!(output.contains("1. 08:15 | Egg | 2 | 156 kcal"), "{}", output);
+}
+
+#[test]
+fn users_from_a_newer_version_are_refused_and_kept() {
+    let dir = DataDir::new("newer-users");
+    fs::create_dir_all(&dir.0).unwrap();
+    let users = "# YADA users format 9\nalice,something new\n";
+    fs::write(dir.0.join("users.txt"), users).unwrap();
+
+    let console = ScriptedConsole { input: Rc::default(), output: Rc::default() };
+    match YadaApplication::open(Config::new(&dir.0), Box::new(console), Box::new(FixedClock)) {
+        Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::Unsupported, "{}", e),
+        Ok(_) => panic!("users from a newer version were accepted"),
+    }
+    assert_eq!(fs::read_to_string(dir.0.join("users.txt")).unwrap(), users);
+}
+
+#[test]
+fn undo_and_redo_survive_a_restart() {
+    let dir = DataDir::new("undo");
+    dir.session(&[&REGISTER[..], &ADD_EGGS, &[EXIT]].concat());
+
+    let output = dir.session(&[&LOGIN[..], &["4", "1", VIEW_LOG, EXIT]].concat());
+    assert!(output.contains("Undone: "), "{}", output);
+    assert!(output.contains("No entries found for this date."), "{}", output);
+
+    let output = dir.session(&[&LOGIN[..], &["4", "2", VIEW_LOG, EXIT]].concat());
+    assert!(output.contains("Redone: "), "{}", output);
+    assert!(output.contains("Total Calories: 156 kcal"), "{}", output);
+
+    let output = dir.session(&[&LOGIN[..], &["4", "2", EXIT]].concat());
+    assert!(output.contains("Nothing to redo."), "{}", output);
+}
+
+#[test]
+fn food_is_logged_on_the_chosen_date() {
+    let dir = DataDir::new("change-date");
+    let output = dir.session(&[&REGISTER[..], &["5", "2025-04-01"], &ADD_EGGS, &[VIEW_LOG, "5", TODAY, VIEW_LOG, EXIT]].concat());
+
+    assert!(output.contains("Date changed to 2025-04-01"), "{}", output);
+    let (earlier, today) = output.split_once(&format!("Date changed to {}", TODAY)).unwrap();
+    assert!(earlier.contains("Food Log for 2025-04-01"), "{}", output);
+    assert!(earlier.contains("Total Calories: 156 kcal"), "{}", output);
+    assert!(today.contains(&format!("Food Log for {}", TODAY)), "{}", output);
+    assert!(today.contains("No entries found for this date."), "{}", output);
+
+    let output = dir.session(&[&LOGIN[..], &["5", "2025-04-01", VIEW_LOG, EXIT]].concat());
+    assert!(output.contains("1. 08:15 | Egg | 2 | 156 kcal"), "{}", output);
+}
+
+#[test]
+fn exercise_counts_against_the_day() {
+    let dir = DataDir::new("exercise");
+    let output = dir.session(&[&REGISTER[..], &ADD_EGGS, &LOG_SWIM, &[VIEW_LOG, EXIT]].concat());
+
+    // 6 MET for half an hour at 60 kg
+    assert!(output.contains("Logged 30 min of Swimming (laps) at 07:00: 180 kcal burned."), "{}", output);
+    assert!(output.contains("1. 07:00 | Swimming (laps) | 30 | 180 kcal"), "{}", output);
+    assert!(output.contains("Total Burned: 180 kcal"), "{}", output);
+    let target: i32 = output.split("Target Calories: ").nth(1)
+        .and_then(|rest| rest.split(' ').next())
+        .and_then(|target| target.parse().ok())
+        .expect("no target in the output");
+    let net = format!("Net Calories (consumed - target - burned): {} kcal", 156 - target - 180);
+    assert!(output.contains(&net), "{}", output);
+
+    let output = dir.session(&[&LOGIN[..], &["4", "1", EXIT]].concat());
+    assert!(output.contains(&format!("Undone: adding exercise swim on {}.", TODAY)), "{}", output);
+    assert!(output.trim_end().rsplit("Current Date").next().unwrap().contains("Burned Calories: 0 kcal"), "{}", output);
+}
+
+#[test]
+fn workouts_track_records_and_weekly_volume() {
+    let dir = DataDir::new("workout");
+    let output = dir.session(&[&REGISTER[..], &LOG_BENCH, &["5", "60", "8", "18:00"],
+                               &LOG_BENCH, &["3", "70", "", "18:10"], &["18", "5", EXIT]].concat());
+
+    // Epley with the reps in reserve: 60 kg × (1 + 7/30)
+    assert!(output.contains("Logged Bench Press: 5 reps with 60 kg at RPE 8.\nEstimated 1RM: 74 kg"), "{}", output);
+    assert!(output.contains("New personal record for Bench Press: heaviest set and estimated 1RM!"), "{}", output);
+    assert!(output.contains("Weekly Volume for 2025-04-07 to 2025-04-13"), "{}", output);
+    assert!(output.contains("chest | 2 | 510 kg"), "{}", output);
+    assert!(output.contains("triceps | 2 | 510 kg"), "{}", output);
+
+    let output = dir.session(&[&LOGIN[..], &["4", "1", "18", "4", EXIT]].concat());
+    assert!(output.contains(&format!("Undone: adding a set of Bench Press on {}.", TODAY)), "{}", output);
+    assert!(output.contains(&format!("Bench Press | 60 kg × 5 ({}) | 74 kg ({})", TODAY, TODAY)), "{}", output);
+}
+
+// A 10 minute run recorded by a watch: 2 km with 15 m of climbing at 150 bpm on average
+const RUN_GPX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="Watch" xmlns="http://www.topografix.com/GPX/1/1"
+     xmlns:gpxtpx="http://www.garmin.com/xmlschemas/TrackPointExtension/v1">
+  <trk><name>Morning Run</name><type>running</type><trkseg>
+    <trkpt lat="51.5000" lon="-0.1000"><ele>10</ele><time>2025-04-06T06:00:00Z</time>
+      <extensions><gpxtpx:TrackPointExtension><gpxtpx:hr>140</gpxtpx:hr></gpxtpx:TrackPointExtension></extensions></trkpt>
+    <trkpt lat="51.5090" lon="-0.1000"><ele>25</ele><time>2025-04-06T06:05:00Z</time>
+      <extensions><gpxtpx:TrackPointExtension><gpxtpx:hr>150</gpxtpx:hr></gpxtpx:TrackPointExtension></extensions></trkpt>
+    <trkpt lat="51.5180" lon="-0.1000"><ele>20</ele><time>2025-04-06T06:10:00Z</time>
+      <extensions><gpxtpx:TrackPointExtension><gpxtpx:hr>160</gpxtpx:hr></gpxtpx:TrackPointExtension></extensions></trkpt>
+  </trkseg></trk>
+</gpx>
+"#;
+
+#[test]
+fn activity_files_are_imported_once() {
+    let dir = DataDir::new("import");
+    fs::create_dir_all(&dir.0).unwrap();
+    let file = dir.0.join("run.gpx");
+    fs::write(&file, RUN_GPX).unwrap();
+    let import = ["17", "4", file.to_str().unwrap()];
+
+    // The run is logged on the local date and time it started
+    let start = DateTime::parse_from_rfc3339("2025-04-06T06:00:00Z").unwrap().with_timezone(&Local);
+    let (date, time) = (start.format("%Y-%m-%d").to_string(), start.format("%H:%M").to_string());
+    let output = dir.session(&[&REGISTER[..], &import, &["5", &date, VIEW_LOG, EXIT]].concat());
+
+    // 9.8 MET for 10 minutes at 60 kg, as the file has no calories
+    let imported = format!("Imported Running (6 mph) on {} at {}: 10 min, 2 km, 15 m climb, avg 150 bpm, 98 kcal burned.", date, time);
+    assert!(output.contains(&imported), "{}", output);
+    assert!(output.contains(&format!("1. {} | Running (6 mph) | 10 | 98 kcal\n   2 km, 15 m climb, avg 150 bpm", time)), "{}", output);
+
+    let output = dir.session(&[&LOGIN[..], &import, &[EXIT]].concat());
+    assert!(output.contains(&format!("Already imported: running started {} {}.", date, time)), "{}", output);
+    assert!(!output.contains("Imported "), "{}", output);
+}
+
+#[test]
+fn deleting_an_account_can_export_everything_first() {
+    let dir = DataDir::new("export");
+    let export = dir.0.join("alice-export");
+    let delete = ["14", "3", "y", export.to_str().unwrap(), "correct horse"];
+    // Kept outside the data directory, where it would look like a user's
+    let output = dir.session_with(Config::new(dir.0.join("data")), &[&REGISTER[..], &ADD_EGGS, &LOG_SWIM, &LOG_BENCH, &["5", "60", "8", "18:00"],
+                                  &["16", "4"], &delete, &REGISTER, &["18", "1", EXIT]].concat());
+    assert!(output.contains("Account alice deleted."), "{}", output);
+    // The new alice starts without the old one's sets
+    assert!(output.trim_end().rsplit("Workout for").next().unwrap().contains("No sets logged for this date."), "{}", output);
+
+    for file in ["profile.txt", "log.txt", "workouts.txt", "journal.txt", "preferences.txt"] {
+        assert!(export.join(file).exists(), "{} was not exported", file);
+    }
+    let exercise = fs::read_to_string(export.join("exercise.txt")).unwrap();
+    assert!(exercise.contains(&format!("{},swim,30,", TODAY)), "{}", exercise);
+}
+
+#[test]
+fn end_of_input_saves_and_exits() {
+    let dir = DataDir::new("eof");
+    let output = dir.session(&[&REGISTER[..], &ADD_EGGS].concat());
+    assert!(output.ends_with("Thank you for using YADA. Goodbye!\n"), "{}", output);
+
+    let output = dir.session(&[&LOGIN[..], &[VIEW_LOG]].concat());
+    assert!(output.contains("Total Calories: 156 kcal"), "{}", output);
+
+    // Input that ends in the middle of a prompt doesn't leave anything half done
+    let output = dir.session(&[&LOGIN[..], &["1", "1", "egg"]].concat());
+    assert!(output.ends_with("Thank you for using YADA. Goodbye!\n"), "{}", output);
+    let output = dir.session(&[&LOGIN[..], &[VIEW_LOG]].concat());
+    assert!(output.contains("Total Calories: 156 kcal"), "{}", output);
+
+    let output = dir.session(&[]);
+    assert!(output.ends_with("Thank you for using YADA. Goodbye!\n"), "{}", output);
+}
+
+#[test]
+fn settings_come_from_the_config_file_and_options() {
+    let dir = DataDir::new("config");
+    fs::create_dir_all(&dir.0).unwrap();
+    let file = dir.0.join("yada.conf");
+    fs::write(&file, "# Test settings\ndata-dir = data\nunits = imperial\nlocale = en-GB\nmethod = mifflin-st-jeor\n").unwrap();
+
+    let mut args: Vec<String> = ["--config", file.to_str().unwrap(), "--locale", "en-US", "report"]
+        .iter().map(|arg| arg.to_string()).collect();
+    // None of the YADA_* variables of whoever runs the tests
+    let config = Config::load_from(&mut args, |_| None).unwrap();
+    assert_eq!(args, ["report"]);
+
+    let register = ["2", "alice", "correct horse", "F", "5'5\"", "30", "132", "2"];
+    let output = dir.session_with(config, &[&register[..], &["5", "04/01/2025", "9", "0", EXIT]].concat());
+    assert!(output.contains("Enter your weight (lb)"), "{}", output);
+    assert!(output.contains("Current Date: 04/08/2025"), "{}", output);
+    assert!(output.contains("Date changed to 04/01/2025"), "{}", output);
+    assert!(output.contains("Current method: Mifflin-St Jeor Equation"), "{}", output);
+    assert!(dir.0.join("data").join("users.txt").exists());
+
+    let mut args = vec!["--units".to_string(), "furlongs".to_string()];
+    assert!(Config::load_from(&mut args, |_| None).is_err());
+}